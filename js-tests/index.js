@@ -6,10 +6,19 @@ import {
   fact,
   rule,
   policy,
+  parseDatalog,
+  PolicySet,
+  Authorizer,
   Biscuit,
+  BlockBuilder,
   KeyPair,
   PrivateKey,
   PublicKey,
+  RevocationList,
+  setLogLevel,
+  setLogger,
+  memoryStats,
+  middleware,
 } from "@biscuit-auth/biscuit-wasm";
 import { test } from "tape";
 // necessary for esm support, see https://docs.rs/getrandom/latest/getrandom/#nodejs-es-module-support
@@ -253,3 +262,830 @@ test("third-party blocks", function(t) {
   t.equal(auth.query(r2).length, 0, "correct number of query results");
   t.end();
 });
+
+test("datalog diagnostics", function(t) {
+  let result = parseDatalog(`user("1234");
+check if user($u);
+allow if`);
+  t.equal(result.diagnostics.length, 1, "one parse error");
+  t.equal(result.diagnostics[0].range.start.line, 2, "error position");
+  t.equal(result.diagnostics[0].message, "unexpected end of input", "readable message");
+
+  let source = `u("é"); check if u(;`;
+  let { range, snippet } = parseDatalog(source).diagnostics[0];
+  t.equal(snippet, ";", "non empty snippet");
+  t.equal(source.slice(range.start.offset, range.end.offset), ";", "offsets in UTF-16 units");
+
+  let parsed = parseDatalog(`user("1234");
+check if user($u);
+allow if true;`);
+  t.equal(parsed.diagnostics.length, 0, "no parse errors");
+  t.deepEqual(parsed.facts.map(f => f.source), [`user("1234")`], "facts");
+  t.deepEqual(parsed.checks[0].range.start, { line: 1, column: 0, offset: 14 }, "check position");
+  t.equal(parsed.policies.length, 1, "policies");
+  t.end();
+});
+
+test("authorizer time", function(t) {
+  let auth = authorizer`allow if true`;
+  auth.setClock(() => new Date("2023-03-28T14:31:06Z"));
+  auth.addTimeNow();
+  auth.authorize();
+  let facts = auth.query(rule`t($t) <- time($t)`);
+  t.equal(facts.length, 1, "one time fact");
+  t.equal(facts[0].toString(), `t(2023-03-28T14:31:06Z)`, "time from the clock");
+
+  let other = authorizer`allow if true`;
+  other.setTime(new Date("2023-01-01T00:00:00Z"));
+  other.authorize();
+  t.equal(
+    other.query(rule`t($t) <- time($t)`)[0].toString(),
+    `t(2023-01-01T00:00:00Z)`,
+    "explicit time"
+  );
+
+  let twice = authorizer`allow if true`;
+  twice.setTime(new Date("2023-01-01T00:00:00Z"));
+  t.throws(() => twice.setTime(new Date("2023-01-02T00:00:00Z")), /AlreadySet/, "time is set once");
+  t.throws(() => twice.addTimeNow(), /AlreadySet/, "time from the clock is set once");
+  twice.authorize();
+  t.equal(twice.query(rule`t($t) <- time($t)`).length, 1, "single time fact");
+  t.end();
+});
+
+test("token expiration", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let builder = biscuit`user("1234");`;
+  builder.expiresAt(new Date("2023-06-01T00:00:00Z"));
+  t.equal(
+    builder.toString(),
+    `// no root key id set
+user("1234");
+check if time($time), $time <= 2023-06-01T00:00:00Z;
+`,
+    "expiration check"
+  );
+
+  let token = builder.build(pk);
+  t.equal(token.getExpiration().toISOString(), "2023-06-01T00:00:00.000Z", "authority expiration");
+
+  let attenuation = block``;
+  attenuation.expiresAt(new Date("2023-05-01T00:00:00Z"));
+  token = token.appendBlock(attenuation);
+  t.equal(token.getExpiration().toISOString(), "2023-05-01T00:00:00.000Z", "earliest expiration");
+
+  t.equal(biscuit`user("1234");`.build(pk).getExpiration(), undefined, "no expiration");
+  t.end();
+});
+
+test("attenuation helpers", function(t) {
+  let builder = block``;
+  builder.restrictOperations(["read", "list"]);
+  builder.restrictResourcePrefix("/files/");
+  builder.restrictAudience("storage");
+  builder.checkRight("/files/a.txt", "read");
+  t.equal(
+    builder.toString(),
+    `check if operation($operation), ["read", "list"].contains($operation);
+check if resource($resource), $resource.starts_with("/files/");
+check if audience("storage");
+check if resource("/files/a.txt"), operation("read");
+`,
+    "generated checks"
+  );
+
+  let error = (operations) => {
+    try {
+      block``.restrictOperations(operations);
+    } catch (e) {
+      return e;
+    }
+  };
+  t.equal(error("read"), "NotAnArray", "a string is rejected");
+  t.deepEqual(error(["read", 1]), { NotAString: { index: 1 } }, "operations must be strings");
+  t.equal(error([]), "Empty", "an empty list is rejected");
+
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let token = biscuit`user("1234");`.build(pk).appendBlock(builder);
+  let auth = authorizer`resource("/files/a.txt"); operation("read"); audience("storage"); allow if true;`;
+  auth.addToken(token);
+  t.equal(auth.authorize(), 0, "authorization succeeded");
+  t.end();
+});
+
+test("facts from objects", function(t) {
+  let auth = new Authorizer();
+  auth.addFactsFromObject({
+    resource: "file1",
+    operation: "read",
+    time: new Date("2023-03-28T14:31:06Z"),
+  });
+  auth.addFactsFromEntries("right", [["file1", "read"], ["file2", "write"]]);
+  try {
+    auth.addFactsFromObject({ user: { id: "1234" } });
+    t.fail("nested objects should be rejected");
+  } catch (e) {
+    t.deepEqual(e, { NestedObject: { name: "user" } }, "nested objects are rejected");
+  }
+  t.throws(
+    () => auth.addFactsFromObject({ user: { id: "1234" } }, { flaten: true }),
+    /unknown field `flaten`/,
+    "unknown options are rejected"
+  );
+  auth.addFactsFromObject({ user: { id: "1234" } }, { flatten: true });
+  for (let name of ["bad name", "", "1user", "user)"]) {
+    try {
+      auth.addFactsFromObject({ [name]: 1 });
+      t.fail(`invalid fact name ${JSON.stringify(name)} should be rejected`);
+    } catch (e) {
+      t.deepEqual(e, { Invalid: { name } }, `invalid fact name ${JSON.stringify(name)}`);
+    }
+  }
+  t.throws(
+    () => auth.addFactsFromObject({ user: { "first name": "a" } }, { flatten: true }),
+    "invalid flattened names are rejected"
+  );
+  t.throws(() => auth.addFactsFromEntries("bad name", [[1]]), "invalid entries names are rejected");
+  auth.addPolicy(policy`allow if true`);
+  auth.authorize();
+
+  t.equal(auth.query(rule`r($r) <- resource($r)`)[0].toString(), `r("file1")`, "object fact");
+  t.equal(auth.query(rule`t($t) <- time($t)`)[0].toString(), `t(2023-03-28T14:31:06Z)`, "date fact");
+  t.equal(auth.query(rule`r($r, $o) <- right($r, $o)`).length, 2, "entries");
+  t.equal(auth.query(rule`u($u) <- user_id($u)`)[0].toString(), `u("1234")`, "flattened fact");
+  t.end();
+});
+
+test("policy set", function(t) {
+  let policies = new PolicySet(`allow if user($u);`);
+  t.throws(() => new PolicySet(`allow if`), "invalid code is rejected");
+
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let token = biscuit`user("1234");`.build(pk);
+
+  for (let i = 0; i < 2; i++) {
+    let auth = policies.createAuthorizer();
+    auth.addToken(token);
+    t.equal(auth.authorize(), 0, "authorization succeeded");
+  }
+
+  let auth = authorizer`check if operation("read");`;
+  auth.mergePolicySet(PolicySet.fromAuthorizer(authorizer`operation("read"); allow if true;`));
+  t.equal(auth.authorize(), 0, "merged policy set");
+
+  let withToken = authorizer`allow if user("1234");`;
+  withToken.addToken(token);
+  withToken.authorize();
+  let fromAuthorizer = PolicySet.fromAuthorizer(withToken);
+  for (let i = 0; i < 2; i++) {
+    let auth = fromAuthorizer.createAuthorizer();
+    auth.addToken(token);
+    t.equal(auth.authorize(), 0, "the token is not copied into the policy set");
+  }
+  t.end();
+});
+
+test("authorizer world dump", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let token = biscuit`user("1234");`.build(pk).appendBlock(block`check if operation("read")`);
+  let auth = authorizer`operation("read"); is_user($u) <- user($u); allow if is_user($u);`;
+  auth.addToken(token);
+
+  let origins = (world, code) =>
+    world.facts.filter((f) => f.code === code).map((f) => f.origins);
+
+  let before = auth.dumpWorld();
+  t.deepEqual(
+    origins(before, `user("1234")`),
+    [[{ type: "block", blockIndex: 0 }]],
+    "token fact origin before authorization"
+  );
+  t.deepEqual(
+    origins(before, `operation("read")`),
+    [[{ type: "authorizer" }]],
+    "authorizer fact origin before authorization"
+  );
+  t.deepEqual(origins(before, `is_user("1234")`), [], "no generated facts before authorization");
+
+  auth.authorize();
+
+  let world = auth.dumpWorld();
+  t.deepEqual(
+    origins(world, `user("1234")`),
+    [[{ type: "block", blockIndex: 0 }]],
+    "token fact origin"
+  );
+  t.deepEqual(
+    origins(world, `operation("read")`),
+    [[{ type: "authorizer" }]],
+    "authorizer fact origin"
+  );
+  t.deepEqual(
+    origins(world, `is_user("1234")`),
+    [[{ type: "block", blockIndex: 0 }, { type: "authorizer" }]],
+    "generated fact origins"
+  );
+  t.equal(world.facts.length, 3, "facts are listed once");
+  t.deepEqual(
+    world.rules,
+    [{ code: `is_user($u) <- user($u)`, origins: [{ type: "authorizer" }] }],
+    "rules"
+  );
+  t.deepEqual(
+    world.checks,
+    [{ code: `check if operation("read")`, origins: [{ type: "block", blockIndex: 1 }] }],
+    "checks"
+  );
+  t.deepEqual(world.policies.map((p) => p.code), [`allow if is_user($u)`], "policies");
+
+  let thirdPartyPk = PrivateKey.fromString(
+    "39c657dbd3f68b09bc8e5fd9887c7cb47a91d1d3883ffbc495ca790552398a92"
+  );
+  let thirdPartyKey = KeyPair.fromPrivateKey(thirdPartyPk).getPublicKey();
+  let external = {
+    type: "block",
+    blockIndex: 1,
+    externalKey: `ed25519/${thirdPartyKey.toString()}`,
+  };
+  let thirdPartyToken = biscuit`u(1);`.build(pk);
+  thirdPartyToken = thirdPartyToken.appendThirdPartyBlock(
+    thirdPartyKey,
+    thirdPartyToken
+      .getThirdPartyRequest()
+      .createBlock(thirdPartyPk, block`g("x"); h($x) <- g($x); check if g("x");`)
+  );
+  let thirdParty = authorizer`allow if true;`;
+  thirdParty.addToken(thirdPartyToken);
+  thirdParty.authorize();
+
+  let thirdPartyWorld = thirdParty.dumpWorld();
+  t.deepEqual(
+    thirdPartyWorld.facts,
+    [
+      { code: `g("x")`, origins: [external] },
+      { code: `h("x")`, origins: [external] },
+      { code: `u(1)`, origins: [{ type: "block", blockIndex: 0 }] },
+    ],
+    "third party facts"
+  );
+  t.deepEqual(
+    thirdPartyWorld.rules,
+    [{ code: `h($x) <- g($x)`, origins: [external] }],
+    "third party rules"
+  );
+  t.deepEqual(
+    thirdPartyWorld.checks,
+    [{ code: `check if g("x")`, origins: [external] }],
+    "third party checks"
+  );
+  t.end();
+});
+
+test("authorizer statistics", function(t) {
+  let auth = authorizer`user("1234"); u($id) <- user($id); allow if u($id);`;
+  auth.authorize();
+  t.ok(auth.iterations() > 0, "iterations");
+  t.ok(auth.factCount() >= 2, "fact count");
+  t.ok(auth.executionTime() >= 0, "execution time");
+
+  let statistics = auth.statistics();
+  t.deepEqual(Object.keys(statistics).sort(), ["executionTime", "factCount", "iterations"], "all statistics");
+  t.equal(statistics.iterations, auth.iterations(), "same iterations");
+  t.equal(statistics.factCount, auth.factCount(), "same fact count");
+  t.end();
+});
+
+test("authorizer default limits", function(t) {
+  t.throws(
+    () => new Authorizer({ limits: { maxFacts: 10 } }),
+    /unknown field `maxFacts`/,
+    "unknown limit keys are rejected"
+  );
+  t.throws(
+    () => new Authorizer({ maxFacts: 1 }),
+    /unknown field `maxFacts`/,
+    "unknown option keys are rejected"
+  );
+
+  let code = `n(1); n(2); n(3); pair($x, $y) <- n($x), n($y); allow if true;`;
+  let auth = new Authorizer({ limits: { max_facts: 5 } });
+  auth.addCode(code);
+  try {
+    auth.authorize();
+    t.fail("authorization should fail");
+  } catch (e) {
+    t.deepEqual(e, { RunLimit: "TooManyFacts" }, "limits apply to authorize");
+  }
+
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  auth = new Authorizer();
+  auth.addToken(biscuit`n(1); n(2); n(3); pair($x, $y) <- n($x), n($y);`.build(pk));
+  auth.addCode(`allow if true;`);
+  auth.setLimits({ max_facts: 5 });
+  try {
+    auth.query(rule`p($x) <- pair($x, 1)`);
+    t.fail("query should fail");
+  } catch (e) {
+    t.deepEqual(e, { RunLimit: "TooManyFacts" }, "limits apply to query");
+  }
+  t.throws(() => auth.setLimits({ limit: {} }), /unknown field `limit`/);
+
+  auth.setLimits(undefined);
+  t.equal(auth.authorize(), 0, "default limits restored");
+  t.end();
+});
+
+test("scoped queries", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let thirdPartyPk = PrivateKey.fromString(
+    "39c657dbd3f68b09bc8e5fd9887c7cb47a91d1d3883ffbc495ca790552398a92"
+  );
+  let thirdPartyRoot = KeyPair.fromPrivateKey(thirdPartyPk);
+
+  let token = biscuit`user("1234");`.build(pk).appendBlock(block`user("5678");`);
+  let thirdPartyBlock = token
+    .getThirdPartyRequest()
+    .createBlock(thirdPartyPk, block`group("admin");`);
+  token = token.appendThirdPartyBlock(thirdPartyRoot.getPublicKey(), thirdPartyBlock);
+
+  let auth = authorizer`allow if true`;
+  auth.addToken(token);
+  auth.authorize();
+
+  t.equal(auth.query(rule`u($u) <- user($u)`).length, 1, "default scope");
+  t.equal(auth.queryAll(rule`u($u) <- user($u)`).length, 2, "all blocks");
+  t.equal(auth.query(rule`g($g) <- group($g)`).length, 0, "untrusted third party");
+  t.equal(
+    auth.queryTrusting(rule`g($g) <- group($g)`, [thirdPartyRoot.getPublicKey()]).length,
+    1,
+    "trusted third party"
+  );
+  t.equal(
+    auth.query(rule`g($g) <- group($g) trusting ${thirdPartyRoot.getPublicKey()}`).length,
+    1,
+    "trusted third party in the rule's code"
+  );
+  let reloaded = authorizer`allow if true`;
+  reloaded.addToken(Biscuit.fromBytes(token.toBytes(), KeyPair.fromPrivateKey(pk).getPublicKey()));
+  reloaded.authorize();
+  t.equal(
+    reloaded.queryTrusting(rule`g($g) <- group($g)`, [thirdPartyRoot.getPublicKey()]).length,
+    1,
+    "trusted third party in a deserialized token"
+  );
+  t.equal(
+    auth.queryTrusting(rule`u($u) <- user($u)`, ["authority"]).length,
+    1,
+    "authority scope"
+  );
+  t.end();
+});
+
+test("single result queries", function(t) {
+  let auth = authorizer`user("1234"); id(9007199254740993); right("read"); right("write"); allow if true;`;
+  auth.authorize();
+
+  t.equal(auth.queryOne(rule`u($u) <- user($u)`).toString(), `u("1234")`, "one fact");
+  let error = (f) => {
+    try {
+      f();
+    } catch (e) {
+      return e;
+    }
+  };
+  t.deepEqual(
+    error(() => auth.queryOne(rule`r($r) <- right($r)`)),
+    { MultipleResults: { count: 2 } },
+    "multiple results"
+  );
+  t.equal(error(() => auth.queryOne(rule`g($g) <- group($g)`)), "NoResult", "no results");
+  t.deepEqual(
+    error(() => auth.queryScalar(rule`u($u, 1) <- user($u)`)),
+    { NotScalar: { terms: 2 } },
+    "more than one term"
+  );
+  t.equal(auth.queryScalar(rule`u($u) <- user($u)`), "1234", "scalar");
+  t.equal(
+    auth.queryScalar(rule`i($i) <- id($i)`),
+    9007199254740993n,
+    "integers are returned without loss of precision"
+  );
+  t.end();
+});
+
+test("revocation list", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let token = biscuit`user("1234");`.build(pk).appendBlock(block`check if true`);
+  let ids = token.getRevocationIdentifiers();
+
+  let list = RevocationList.fromString(`# revoked tokens\n${ids[1]}\n`);
+  t.equal(list.count(), 1, "parsed list");
+  t.ok(token.isRevoked(list), "token is revoked");
+  t.notOk(token.isRevoked(RevocationList.fromString("[]")), "token is not revoked");
+
+  let auth = authorizer`allow if true`;
+  auth.addToken(token);
+  auth.setRevocationList(list);
+  try {
+    auth.authorize();
+    t.fail("authorization should fail");
+  } catch (e) {
+    t.deepEqual(e, { Revoked: { block: 1, id: ids[1] } }, "revocation error");
+  }
+
+  auth.clearRevocationList();
+  t.equal(auth.authorize(), 0, "authorization without revocation list");
+  t.end();
+});
+
+test("revocation identifiers", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let thirdPartyPk = PrivateKey.fromString(
+    "39c657dbd3f68b09bc8e5fd9887c7cb47a91d1d3883ffbc495ca790552398a92"
+  );
+  let thirdPartyRoot = KeyPair.fromPrivateKey(thirdPartyPk);
+  let token = biscuit`user("1234");`.build(pk);
+  let thirdPartyBlock = token
+    .getThirdPartyRequest()
+    .createBlock(thirdPartyPk, block`group("admin");`);
+  token = token.appendThirdPartyBlock(thirdPartyRoot.getPublicKey(), thirdPartyBlock);
+
+  let ids = token.getRevocationIdentifiers();
+  let bytes = token.getRevocationIdentifiersBytes();
+  t.equal(bytes.length, 2, "one identifier per block");
+  t.ok(bytes[0] instanceof Uint8Array, "raw identifiers");
+  t.equal(Buffer.from(bytes[1]).toString("hex"), ids[1], "same identifiers");
+
+  t.deepEqual(
+    token.getRevocationInfo(),
+    [
+      { blockIndex: 0, id: ids[0] },
+      {
+        blockIndex: 1,
+        id: ids[1],
+        externalKey: thirdPartyRoot.getPublicKey().toDatalogParameter(),
+      },
+    ],
+    "revocation info"
+  );
+  t.end();
+});
+
+test("token size", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let token = biscuit`user("1234");`.build(pk);
+  t.equal(token.size(), token.toBytes().length, "token size");
+
+  let attenuation = block`check if operation("read");`;
+  let estimate = attenuation.estimateSize();
+  t.ok(estimate > 0, "block size estimate");
+
+  let attenuated = token.appendBlock(attenuation, { maxTotalSize: 4096 });
+  let sizes = attenuated.getBlockSizes();
+  t.equal(sizes.length, 2, "one size per block");
+  t.ok(sizes.reduce((a, b) => a + b) <= attenuated.size(), "block sizes");
+
+  try {
+    token.appendBlock(attenuation, { maxTotalSize: token.size() });
+    t.fail("append should fail");
+  } catch (e) {
+    t.ok(e.TooLarge, "size budget exceeded");
+  }
+  t.throws(
+    () => token.appendBlock(attenuation, { maxSize: 4096 }),
+    /unknown field `maxSize`/,
+    "unknown option keys are rejected"
+  );
+  t.equal(
+    attenuated.getBlockSizes().length,
+    attenuated.countBlocks(),
+    "block sizes match the block count"
+  );
+  let sealed = attenuated.sealToken();
+  t.equal(sealed.size(), sealed.toBytes().length, "sealed token size");
+  t.end();
+});
+
+test("sealed tokens", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let token = biscuit`user("1234");`.build(pk);
+  t.notOk(token.isSealed(), "token is not sealed");
+
+  let sealed = Biscuit.fromBase64(token.sealToken().toBase64(), KeyPair.fromPrivateKey(pk).getPublicKey());
+  t.ok(sealed.isSealed(), "parsed token is sealed");
+  try {
+    sealed.appendBlock(block`check if true`);
+    t.fail("append should fail");
+  } catch (e) {
+    t.equal(e, "AppendOnSealed", "dedicated error");
+  }
+  try {
+    sealed.appendBlockWithKeyPair(block`check if true`, new KeyPair());
+    t.fail("append with a key pair should fail");
+  } catch (e) {
+    t.equal(e, "AppendOnSealed", "dedicated error with a key pair");
+  }
+  t.end();
+});
+
+test("reproducible tokens", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let blockKey = KeyPair.fromPrivateKey(
+    PrivateKey.fromString(
+      "39c657dbd3f68b09bc8e5fd9887c7cb47a91d1d3883ffbc495ca790552398a92"
+    )
+  );
+
+  let make = () =>
+    biscuit`user("1234");`
+      .buildWithRng(pk, 42)
+      .appendBlockWithKeyPair(block`check if true`, blockKey)
+      .toBase64();
+
+  t.equal(make(), make(), "same bytes");
+  t.equal(
+    biscuit`user("1234");`.buildWithRng(pk, 42).toBase64(),
+    "En0KEwoEMTIzNBgDIgkKBwgKEgMYgAgSJAgAEiB47aIboEoV4gAP6IEP4-VnQdI7ua5Eqp1bsht2Z1_zSxpA-2CQjnQpOpYyMmHgs2S579p6q5yGfajdeLOEejpGrt9QDdB9pBBIclg5QgHjBubvK0AUChhqu3-Ifg7wO2b0CSIiCiB4SLXXEbyYg5ljF6P5yQJp1WdxAF1UChkYSTnJ6NDbKg==",
+    "same bytes across versions"
+  );
+  t.end();
+});
+
+test("log level configuration", function (t) {
+  setLogLevel("debug");
+  setLogLevel("OFF");
+  t.throws(() => setLogLevel("verbose"), "unknown level");
+  t.end();
+});
+
+test("log sink configuration", function (t) {
+  const records = [];
+  setLogger({ level: "trace", sink: (record) => records.push(record) });
+  t.throws(() => setLogger({ level: "verbose" }), "unknown level");
+  t.throws(() => setLogger({ sink: "console" }), "sink must be a function");
+  setLogger({ level: "off" });
+  setLogger({ level: "trace", sink: (record) => records.push(record) });
+  authorizer`allow if true`.authorize();
+  t.deepEqual(
+    records,
+    [{ level: "debug", target: "biscuit_wasm::authorizer", message: "authorization succeeded with policy 0" }],
+    "records are sent to the sink"
+  );
+
+  // the sink can reconfigure the logger
+  setLogger({ level: "debug", sink: () => setLogger() });
+  authorizer`allow if true`.authorize();
+  authorizer`allow if true`.authorize();
+  t.equal(records.length, 1, "the logger was reset by the sink");
+  t.end();
+});
+
+test("memory stats", function (t) {
+  const before = memoryStats();
+  t.ok(before.heapSize > 0, "heap size");
+
+  const keypair = new KeyPair();
+  const publicKey = keypair.getPublicKey();
+  t.equal(
+    memoryStats().objects.KeyPair,
+    before.objects.KeyPair + 1,
+    "keypair is live"
+  );
+
+  keypair.free();
+  publicKey.free();
+  t.deepEqual(memoryStats().objects, before.objects, "objects were freed");
+
+  const list = new RevocationList();
+  const auth = new Authorizer();
+  auth.setRevocationList(list);
+  list.free();
+  t.equal(
+    memoryStats().objects.RevocationList,
+    before.objects.RevocationList,
+    "authorizers do not hold a revocation list object"
+  );
+  auth.free();
+
+  if (typeof Symbol.dispose === "symbol") {
+    const other = new KeyPair();
+    other[Symbol.dispose]();
+    t.deepEqual(memoryStats().objects, before.objects, "object was disposed");
+  }
+  t.end();
+});
+
+test("builders can be reused", function (t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let root = KeyPair.fromPrivateKey(pk);
+
+  let builder = biscuit`user(1234);`;
+  let template = builder.clone();
+  let first = builder.build(pk);
+  let second = builder.build(pk);
+  t.equal(
+    first.getBlockSource(0),
+    second.getBlockSource(0),
+    "same authority block"
+  );
+  t.equal(template.build(pk).getBlockSource(0), first.getBlockSource(0));
+
+  let token = first.clone();
+  first.free();
+  t.equal(token.countBlocks(), 1, "the copy outlives the original");
+
+  let request = token.getThirdPartyRequest();
+  let external = new KeyPair();
+  let thirdPartyBlock = request.createBlock(
+    external.getPrivateKey(),
+    block`external(true);`
+  );
+  let otherBlock = request.createBlock(
+    external.getPrivateKey(),
+    block`external(false);`
+  );
+  t.notEqual(
+    otherBlock.toBase64(),
+    thirdPartyBlock.toBase64(),
+    "the request can create several blocks"
+  );
+  t.ok(
+    token
+      .appendThirdPartyBlock(external.getPublicKey(), request.clone().createBlock(
+        external.getPrivateKey(),
+        block`external(true);`
+      ))
+      .getBlockSource(1)
+      .includes("external(true)"),
+    "a copy of the request creates valid blocks"
+  );
+  t.equal(thirdPartyBlock.toBase64(), thirdPartyBlock.toBase64());
+
+  let attenuated = token.appendThirdPartyBlock(
+    external.getPublicKey(),
+    thirdPartyBlock.clone()
+  );
+  t.equal(attenuated.countBlocks(), 2);
+  t.equal(root.getPublicKey().toString(), root.clone().getPublicKey().toString());
+  t.end();
+});
+
+test("datalog elements can be added to several builders", function (t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let userFact = fact`user(1234)`;
+  let readRule = rule`can_read($r) <- right($r, "read")`;
+  let operationCheck = check`check if operation("read")`;
+
+  let builder = biscuit``;
+  builder.addFact(userFact);
+  builder.addRule(readRule);
+  builder.addCheck(operationCheck);
+
+  let first = block``;
+  first.addFact(userFact);
+  first.addRule(readRule);
+  first.addCheck(operationCheck);
+  let second = block``;
+  second.addFact(userFact.clone());
+  second.addRule(readRule.clone());
+  second.addCheck(operationCheck.clone());
+  t.equal(first.toString(), second.toString(), "same block content");
+
+  let auth = authorizer``;
+  auth.addFact(userFact);
+  auth.addCheck(operationCheck);
+  auth.addPolicy(policy`allow if true`.clone());
+
+  let token = builder.build(pk).appendBlock(first);
+  t.equal(token.countBlocks(), 2);
+  t.equal(userFact.toString(), "user(1234)", "the fact is still usable");
+  t.end();
+});
+
+test("policy fragments between blocks and authorizers", function (t) {
+  let first = block`user(1234);`;
+  let second = block`check if operation("read");`;
+  first.merge(second);
+  t.equal(
+    first.toString(),
+    block`user(1234); check if operation("read");`.toString(),
+    "merged block"
+  );
+
+  let auth = Authorizer.fromBlock(first);
+  auth.addCode(`operation("read"); allow if user(1234);`);
+  t.equal(auth.authorize(), 0, "authorized with the block's content");
+
+  let exported = BlockBuilder.fromAuthorizer(
+    authorizer`right("file1", "read"); check if right("file1", "read"); allow if true;`
+  );
+  t.equal(
+    exported.toString(),
+    block`right("file1", "read"); check if right("file1", "read");`.toString(),
+    "policies are dropped"
+  );
+
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let token = biscuit`user(1234); check if operation("read");`.build(pk);
+  let withToken = authorizer`operation("read"); is_user($u) <- user($u); allow if is_user(1234);`;
+  withToken.addToken(token);
+  t.equal(withToken.authorize(), 0, "authorized");
+  t.equal(
+    BlockBuilder.fromAuthorizer(withToken).toString(),
+    block`operation("read"); is_user($u) <- user($u);`.toString(),
+    "the token's content and generated facts are not exported"
+  );
+  t.end();
+});
+
+test("express middleware", function(t) {
+  let pk = PrivateKey.fromString(
+    "473b5189232f3f597b5c2f3f9b0d5e28b1ee4e7cce67ec6b7fbf5984157a6b97"
+  );
+  let token = biscuit`user("1234");`.build(pk);
+
+  let run = (options, makeAuthorizer) => {
+    let outcome;
+    let req = { headers: { authorization: `Bearer ${token.toBase64()}` } };
+    middleware({
+      publicKey: KeyPair.fromPrivateKey(pk).getPublicKey(),
+      onError: (errorType, error) => {
+        outcome = `${errorType} ${error}`;
+      },
+      ...options,
+    })(makeAuthorizer)(req, {}, () => {
+      outcome = req.biscuit.result;
+    });
+    return outcome;
+  };
+
+  // the middleware uses the default time limit of 1ms, which the first,
+  // not yet optimized, authorization can exceed on slow machines
+  let warmup = authorizer`allow if user($u);`;
+  warmup.addToken(token);
+  warmup.authorizeWithLimits({ max_time_micro: 1_000_000 });
+
+  t.equal(run({}, authorizer`allow if user($u);`), 0, "authorizer");
+  t.equal(run({}, new PolicySet(`allow if user($u);`)), 0, "policy set");
+  t.equal(
+    run(
+      {
+        priorityAuthorizer: new PolicySet(`check if user("1234");`),
+        fallbackAuthorizer: authorizer`allow if true;`,
+      },
+      (req) => new PolicySet(`deny if user("5678");`)
+    ),
+    1,
+    "priority and fallback authorizers"
+  );
+
+  let reported = [];
+  let denied = run(
+    { onStatistics: (statistics) => reported.push(statistics) },
+    authorizer`deny if user("1234");`
+  );
+  t.ok(denied.startsWith("authorization "), "authorization error");
+  t.equal(reported.length, 1, "statistics reported once");
+  t.ok(reported[0].factCount > 0, "statistics content");
+  t.equal(
+    run(
+      {
+        onStatistics: () => {
+          throw new Error("statistics");
+        },
+      },
+      authorizer`deny if user("1234");`
+    ),
+    denied,
+    "the authorization error is not replaced"
+  );
+  t.end();
+});
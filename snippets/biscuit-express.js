@@ -1,3 +1,5 @@
+import { PolicySet } from "./biscuit_bg.js";
+
 export function middleware(options) {
   // assumes the token is in the `Authorization` header,
   // prefixed with `Bearer `
@@ -39,15 +41,24 @@ export function middleware(options) {
     }
   };
 
+  const mergeAuthorizer = (authorizer, other) => {
+    if (other instanceof PolicySet) {
+      authorizer.mergePolicySet(other);
+    } else {
+      authorizer.merge(other);
+    }
+  };
+
   const applyAuthorizerBuilder = (authorizer, makeAuthorizer, req) => {
     if (typeof makeAuthorizer === "function") {
-      authorizer.merge(makeAuthorizer(req));
+      mergeAuthorizer(authorizer, makeAuthorizer(req));
     } else if (makeAuthorizer) {
-      authorizer.merge(makeAuthorizer);
+      mergeAuthorizer(authorizer, makeAuthorizer);
     }
   };
 
-  const { publicKey, priorityAuthorizer, fallbackAuthorizer } = options;
+  const { publicKey, priorityAuthorizer, fallbackAuthorizer, onStatistics } =
+    options;
   const tokenExtractor = options.tokenExtractor ?? defaultExtractor;
   const tokenParser = options.tokenParser ?? defaultParser;
   const onError = options.onError ?? defaultOnError;
@@ -65,11 +76,46 @@ export function middleware(options) {
             applyAuthorizerBuilder(authorizer, fallbackAuthorizer, req);
 
             authorizer.addToken(token);
-            const result = authorizer.authorize();
+            let result;
+            let failed = false;
+            let failure;
+            try {
+              result = authorizer.authorize();
+            } catch (e) {
+              failed = true;
+              failure = e;
+            }
+
+            // the statistics are computed at most once, when they are read
+            let statistics;
+            const getStatistics = () => {
+              if (statistics === undefined) {
+                statistics = authorizer.statistics();
+              }
+              return statistics;
+            };
+
+            if (onStatistics) {
+              try {
+                onStatistics(getStatistics(), req);
+              } catch (e) {
+                // the authorization error is reported instead
+                if (!failed) {
+                  throw e;
+                }
+              }
+            }
+            if (failed) {
+              throw failure;
+            }
+
             req.biscuit = {
               token,
               authorizer,
               result,
+              get statistics() {
+                return getStatistics();
+              },
             };
             next();
           } catch (e) {
@@ -1,8 +1,12 @@
 /**
  * Function building an authorizer from a request, or an authorizer directly,
- * when it doesn't depend on the request
+ * when it doesn't depend on the request. A `PolicySet` can be used in place of
+ * an authorizer to avoid parsing the same policies for each request.
  */
-export type AuthorizerBuilder = Authorizer | ((req: any) => Authorizer);
+export type AuthorizerBuilder =
+  | Authorizer
+  | PolicySet
+  | ((req: any) => Authorizer | PolicySet);
 
 /**
  * Phase of the authorization process where the error happened.
@@ -12,6 +16,24 @@ export type AuthorizerBuilder = Authorizer | ((req: any) => Authorizer);
  */
 export type ErrorType = "extraction" | "verification" | "authorization";
 
+/**
+ * Statistics about the execution of the authorizer, useful to tune run limits.
+ */
+export type AuthorizerStatistics = {
+  /**
+   * Time spent running the authorizer, in microseconds
+   */
+  executionTime: number;
+  /**
+   * Number of iterations needed to generate all the facts
+   */
+  iterations: number;
+  /**
+   * Number of facts in the authorizer
+   */
+  factCount: number;
+};
+
 /**
  * Phase of the authorization process where the error happened.
  * Extraction is extracting a token string from the HTTP request.
@@ -54,6 +76,13 @@ export type BiscuitMiddlewareOptions = {
     res: any,
     next: any
   ) => void;
+
+  /**
+   * Called after each authorization, successful or not, with the authorizer
+   * execution statistics. They are also available in `req.biscuit.statistics`
+   * for successful authorizations.
+   */
+  onStatistics?: (statistics: AuthorizerStatistics, req: any) => void;
 };
 
 export function middleware(
@@ -0,0 +1,61 @@
+/**
+ * Memory used by the wasm module
+ */
+export type MemoryStats = {
+  /**
+   * number of objects not freed yet, by class
+   */
+  objects: Record<string, number>;
+  /**
+   * size of the wasm heap, in bytes
+   */
+  heapSize: number;
+};
+
+// `Symbol.dispose` is added to the classes by `resources.js`, the interfaces
+// are merged with the class declarations generated by wasm-bindgen
+export interface Authorizer {
+  [Symbol.dispose](): void;
+}
+export interface Biscuit {
+  [Symbol.dispose](): void;
+}
+export interface BiscuitBuilder {
+  [Symbol.dispose](): void;
+}
+export interface BlockBuilder {
+  [Symbol.dispose](): void;
+}
+export interface Check {
+  [Symbol.dispose](): void;
+}
+export interface Fact {
+  [Symbol.dispose](): void;
+}
+export interface KeyPair {
+  [Symbol.dispose](): void;
+}
+export interface Policy {
+  [Symbol.dispose](): void;
+}
+export interface PolicySet {
+  [Symbol.dispose](): void;
+}
+export interface PrivateKey {
+  [Symbol.dispose](): void;
+}
+export interface PublicKey {
+  [Symbol.dispose](): void;
+}
+export interface RevocationList {
+  [Symbol.dispose](): void;
+}
+export interface Rule {
+  [Symbol.dispose](): void;
+}
+export interface ThirdPartyBlock {
+  [Symbol.dispose](): void;
+}
+export interface ThirdPartyRequest {
+  [Symbol.dispose](): void;
+}
@@ -0,0 +1,13 @@
+import * as bindings from "./biscuit_bg.js";
+
+// `using` declarations call `Symbol.dispose`, which frees the object
+if (typeof Symbol.dispose === "symbol") {
+  for (const value of Object.values(bindings)) {
+    if (
+      typeof value === "function" &&
+      typeof value.prototype?.free === "function"
+    ) {
+      value.prototype[Symbol.dispose] = value.prototype.free;
+    }
+  }
+}
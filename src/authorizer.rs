@@ -1,11 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use biscuit_auth as biscuit;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
 
-use crate::{Biscuit, BlockBuilder, Check, Fact, Policy, PublicKey, Rule, Term};
+use crate::memory::Live;
+use crate::{
+    check_fact_name, check_revoked, date_to_timestamp, js_value_to_term, object_to_facts,
+    revocation_ids, term_to_js, Biscuit, BlockBuilder, Check, Fact, Policy, PublicKey,
+    RevocationList, Rule, Term,
+};
 
 #[derive(Deserialize)]
 pub struct RunLimits {
@@ -15,6 +20,14 @@ pub struct RunLimits {
 }
 
 impl RunLimits {
+    const FIELDS: &'static [&'static str] = &["max_facts", "max_iterations", "max_time_micro"];
+
+    /// Parses limits from a JS object, rejecting unknown keys
+    pub fn from_js(value: JsValue) -> Result<RunLimits, JsValue> {
+        check_keys(&value, RunLimits::FIELDS)?;
+        Ok(serde_wasm_bindgen::from_value(value)?)
+    }
+
     pub fn to_rust_limits(&self) -> biscuit::datalog::RunLimits {
         let defaults = biscuit::datalog::RunLimits::default();
         biscuit::datalog::RunLimits {
@@ -28,29 +41,125 @@ impl RunLimits {
     }
 }
 
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "AuthorizerStatistics")]
+    pub type AuthorizerStatistics;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Statistics {
+    execution_time: f64,
+    iterations: f64,
+    fact_count: usize,
+}
+
+/// Error returned when setting the time of an authorizer that already has one
+#[derive(Serialize)]
+pub enum TimeError {
+    AlreadySet,
+}
+
+/// Error returned by `queryOne` and `queryScalar` when the results do not have
+/// the expected shape
+#[derive(Serialize)]
+pub enum QueryError {
+    NoResult,
+    MultipleResults { count: usize },
+    NotScalar { terms: usize },
+}
+
+#[derive(Deserialize)]
+pub struct FactsOptions {
+    #[serde(default)]
+    pub flatten: bool,
+}
+
 /// The Authorizer verifies a request according to its policies and the provided token
 #[wasm_bindgen]
 //#[derive(Default)]
-pub struct Authorizer(pub(crate) biscuit::Authorizer);
+pub struct Authorizer {
+    pub(crate) inner: biscuit::Authorizer,
+    clock: Option<js_sys::Function>,
+    /// set once a `time` fact was added by `setTime` or `addTimeNow`
+    has_time: bool,
+    /// token added to the authorizer, its blocks are listed by `dumpWorld`
+    token: Option<biscuit::Biscuit>,
+    /// external keys of the token's blocks, used to describe origins
+    external_keys: Vec<Option<String>>,
+    /// revocation identifiers of the token's blocks
+    revocation_ids: Vec<String>,
+    /// revoked identifiers, copied from the `RevocationList` so that it can be freed
+    revocation_list: Option<BTreeSet<String>>,
+    _live: Live<Authorizer>,
+}
 
 #[wasm_bindgen]
 impl Authorizer {
+    /// Creates an authorizer
+    ///
+    /// The options can contain the default `limits` used by `authorize` and `query`
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Authorizer {
-        Authorizer(biscuit::Authorizer::new())
+    pub fn new(options: JsValue) -> Result<Authorizer, JsValue> {
+        let mut inner = biscuit::Authorizer::new();
+        if !options.is_undefined() {
+            check_keys(&options, &["limits"])?;
+            let limits = js_sys::Reflect::get(&options, &JsValue::from_str("limits"))?;
+            if !limits.is_undefined() {
+                inner.set_limits(RunLimits::from_js(limits)?.to_rust_limits());
+            }
+        }
+
+        Ok(Authorizer {
+            inner,
+            ..Default::default()
+        })
+    }
+
+    /// Returns a copy of the authorizer, with its token, clock, limits and revocation list
+    pub fn clone(&self) -> Authorizer {
+        Authorizer {
+            inner: self.inner.clone(),
+            clock: self.clock.clone(),
+            has_time: self.has_time,
+            token: self.token.clone(),
+            external_keys: self.external_keys.clone(),
+            revocation_ids: self.revocation_ids.clone(),
+            revocation_list: self.revocation_list.clone(),
+            _live: Live::new(),
+        }
+    }
+
+    /// Sets the limits used by `authorize` and `query`
+    ///
+    /// Passing `undefined` restores the default limits
+    #[wasm_bindgen(js_name = setLimits)]
+    pub fn set_limits(&mut self, limits: JsValue) -> Result<(), JsValue> {
+        let limits = if limits.is_undefined() {
+            biscuit::datalog::RunLimits::default()
+        } else {
+            RunLimits::from_js(limits)?.to_rust_limits()
+        };
+        self.inner.set_limits(limits);
+        Ok(())
     }
 
     #[wasm_bindgen(js_name = addToken)]
     pub fn add_token(&mut self, token: &Biscuit) -> Result<(), JsValue> {
-        self.0
+        self.inner
             .add_token(&token.0)
-            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        self.token = Some(token.0.clone());
+        self.external_keys = external_keys(&token.0);
+        self.revocation_ids = revocation_ids(&token.0);
+        Ok(())
     }
 
     /// Adds a Datalog fact
     #[wasm_bindgen(js_name = addFact)]
     pub fn add_fact(&mut self, fact: &Fact) -> Result<(), JsValue> {
-        self.0
+        self.inner
             .add_fact(fact.0.clone())
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
@@ -58,7 +167,7 @@ impl Authorizer {
     /// Adds a Datalog rule
     #[wasm_bindgen(js_name = addRule)]
     pub fn add_rule(&mut self, rule: &Rule) -> Result<(), JsValue> {
-        self.0
+        self.inner
             .add_rule(rule.0.clone())
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
@@ -68,7 +177,7 @@ impl Authorizer {
     /// All checks, from authorizer and token, must be validated to authorize the request
     #[wasm_bindgen(js_name = addCheck)]
     pub fn add_check(&mut self, check: &Check) -> Result<(), JsValue> {
-        self.0
+        self.inner
             .add_check(check.0.clone())
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
@@ -80,7 +189,7 @@ impl Authorizer {
     /// succeed
     #[wasm_bindgen(js_name = addPolicy)]
     pub fn add_policy(&mut self, policy: &Policy) -> Result<(), JsValue> {
-        self.0
+        self.inner
             .add_policy(policy.0.clone())
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
@@ -88,19 +197,33 @@ impl Authorizer {
     /// Merges the contents of another authorizer
     #[wasm_bindgen(js_name = merge)]
     pub fn merge(&mut self, other: &Authorizer) {
-        self.0.merge(other.0.clone())
+        self.inner.merge(other.inner.clone())
+    }
+
+    /// Merges the contents of a policy set
+    #[wasm_bindgen(js_name = mergePolicySet)]
+    pub fn merge_policy_set(&mut self, other: &PolicySet) {
+        self.inner.merge(other.0.clone())
+    }
+
+    /// Creates an authorizer containing the facts, rules and checks of a block builder
+    #[wasm_bindgen(js_name = fromBlock)]
+    pub fn from_block(block: &BlockBuilder) -> Authorizer {
+        let mut authorizer = Authorizer::default();
+        authorizer.merge_block(block);
+        authorizer
     }
 
     /// Merges the contents of a block builder
     #[wasm_bindgen(js_name = mergeBlock)]
     pub fn merge_block(&mut self, other: &BlockBuilder) {
-        self.0.merge_block(other.0.clone())
+        self.inner.merge_block(other.0.clone())
     }
 
     /// Adds facts, rules, checks and policies as one code block
     #[wasm_bindgen(js_name = addCode)]
     pub fn add_code(&mut self, source: &str) -> Result<(), JsValue> {
-        self.0
+        self.inner
             .add_code(source)
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
@@ -127,44 +250,220 @@ impl Authorizer {
             .map(|(k, p)| (k, p.0))
             .collect::<HashMap<_, _>>();
 
-        self.0
+        self.inner
             .add_code_with_params(source, parameters, scope_parameters)
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
+    /// Adds one fact per property of an object, named after the property
+    ///
+    /// `{ resource: "file1", operation: "read" }` generates `resource("file1")` and
+    /// `operation("read")`. Nested objects are rejected, unless the `flatten` option is
+    /// set: `{ user: { id: "1234" } }` then generates `user_id("1234")`
+    #[wasm_bindgen(js_name = addFactsFromObject)]
+    pub fn add_facts_from_object(
+        &mut self,
+        object: &js_sys::Object,
+        options: JsValue,
+    ) -> Result<(), JsValue> {
+        check_keys(&options, &["flatten"])?;
+        let options: Option<FactsOptions> = serde_wasm_bindgen::from_value(options)?;
+        let flatten = options.map(|o| o.flatten).unwrap_or_default();
+
+        let mut facts = Vec::new();
+        object_to_facts(None, object, flatten, &mut facts)?;
+        for fact in facts {
+            self.inner
+                .add_fact(fact)
+                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        }
+        Ok(())
+    }
+
+    /// Adds one fact with the given name per row
+    ///
+    /// Each row is an array containing the fact's terms, or a single term:
+    /// `addFactsFromEntries("right", [["file1", "read"], ["file2", "write"]])` generates
+    /// `right("file1", "read")` and `right("file2", "write")`
+    #[wasm_bindgen(js_name = addFactsFromEntries)]
+    pub fn add_facts_from_entries(
+        &mut self,
+        name: &str,
+        rows: &js_sys::Array,
+    ) -> Result<(), JsValue> {
+        check_fact_name(name)?;
+        for row in rows.iter() {
+            let terms = if js_sys::Array::is_array(&row) {
+                js_sys::Array::from(&row)
+                    .iter()
+                    .map(js_value_to_term)
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                vec![js_value_to_term(row)?]
+            };
+
+            self.inner
+                .add_fact(biscuit::builder::fact(name, &terms))
+                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        }
+        Ok(())
+    }
+
+    /// Sets the clock used by `setTime` and `addTimeNow`
+    ///
+    /// The clock is a function returning a `Date` (or a number of milliseconds since the
+    /// epoch). Passing `undefined` restores the system clock
+    #[wasm_bindgen(js_name = setClock)]
+    pub fn set_clock(&mut self, clock: Option<js_sys::Function>) {
+        self.clock = clock;
+    }
+
+    /// Adds a `time` fact with the provided date, or the current time of the clock if
+    /// no date is provided
+    ///
+    /// Facts cannot be removed from an authorizer, so the time can only be set once:
+    /// calling it again fails with a `TimeError`
+    #[wasm_bindgen(js_name = setTime)]
+    pub fn set_time(&mut self, date: Option<js_sys::Date>) -> Result<(), JsValue> {
+        if self.has_time {
+            return Err(serde_wasm_bindgen::to_value(&TimeError::AlreadySet).unwrap());
+        }
+
+        let date = match date {
+            Some(date) => date,
+            None => self.now()?,
+        };
+
+        let timestamp = date_to_timestamp(&date)?;
+        self.inner
+            .add_fact(biscuit::builder::fact(
+                "time",
+                &[biscuit::builder::Term::Date(timestamp)],
+            ))
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        self.has_time = true;
+        Ok(())
+    }
+
+    /// Adds a `time` fact with the current time of the clock
+    #[wasm_bindgen(js_name = addTimeNow)]
+    pub fn add_time_now(&mut self) -> Result<(), JsValue> {
+        self.set_time(None)
+    }
+
     /// Runs the authorization checks and policies
     ///
     /// Returns the index of the matching allow policy, or an error containing the matching deny
     /// policy or a list of the failing checks
     #[wasm_bindgen(js_name = authorize)]
     pub fn authorize(&mut self) -> Result<usize, JsValue> {
-        self.0
-            .authorize()
-            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+        self.check_revocation()?;
+        let result = self.inner.authorize();
+        log_result(&result);
+        result.map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
     #[wasm_bindgen(js_name = authorizeWithLimits)]
     pub fn authorizer_with_limits(&mut self, limits: JsValue) -> Result<usize, JsValue> {
-        let limits: RunLimits = serde_wasm_bindgen::from_value(limits)?;
-        self.0
-            .authorize_with_limits(limits.to_rust_limits())
-            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+        let limits = RunLimits::from_js(limits)?;
+        self.check_revocation()?;
+        let result = self.inner.authorize_with_limits(limits.to_rust_limits());
+        log_result(&result);
+        result.map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+    }
+
+    /// Sets the list of revoked identifiers
+    ///
+    /// Authorization fails with a `Revoked` error if one of the token's blocks was revoked
+    #[wasm_bindgen(js_name = setRevocationList)]
+    pub fn set_revocation_list(&mut self, list: &RevocationList) {
+        self.revocation_list = Some(list.0.clone());
+    }
+
+    /// Removes the list of revoked identifiers
+    #[wasm_bindgen(js_name = clearRevocationList)]
+    pub fn clear_revocation_list(&mut self) {
+        self.revocation_list = None;
+    }
+
+    /// Returns the execution time, iterations and fact count of the authorizer
+    ///
+    /// Each statistic is read from a snapshot of the authorizer's world, this gets
+    /// them all from a single snapshot
+    #[wasm_bindgen(js_name = statistics)]
+    pub fn statistics(&self) -> Result<AuthorizerStatistics, JsValue> {
+        let snapshot = self.snapshot()?;
+        let statistics = Statistics {
+            execution_time: snapshot.execution_time as f64 / 1000.0,
+            iterations: snapshot.world.iterations as f64,
+            fact_count: snapshot
+                .world
+                .generated_facts
+                .iter()
+                .map(|f| f.facts.len())
+                .sum(),
+        };
+        Ok(serde_wasm_bindgen::to_value(&statistics)?.unchecked_into())
+    }
+
+    /// Returns the total time spent running the authorizer's Datalog engine, in microseconds
+    #[wasm_bindgen(js_name = executionTime)]
+    pub fn execution_time(&self) -> Result<f64, JsValue> {
+        Ok(self.snapshot()?.execution_time as f64 / 1000.0)
+    }
+
+    /// Returns the number of iterations of the authorizer's Datalog engine
+    #[wasm_bindgen(js_name = iterations)]
+    pub fn iterations(&self) -> Result<f64, JsValue> {
+        Ok(self.snapshot()?.world.iterations as f64)
+    }
+
+    /// Returns the number of facts in the authorizer's world, including generated facts
+    #[wasm_bindgen(js_name = factCount)]
+    pub fn fact_count(&self) -> Result<usize, JsValue> {
+        Ok(self
+            .snapshot()?
+            .world
+            .generated_facts
+            .iter()
+            .map(|f| f.facts.len())
+            .sum())
     }
 
     /// Executes a query over the authorizer
     #[wasm_bindgen(js_name = query)]
     pub fn query(&mut self, rule: &Rule) -> Result<js_sys::Array, JsValue> {
-        let v: Vec<biscuit::builder::Fact> = self
-            .0
-            .query(rule.0.clone())
-            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        Ok(facts_to_array(self.run_query(rule.0.clone())?))
+    }
 
-        let facts = js_sys::Array::new();
-        for f in v.into_iter().map(Fact) {
-            facts.push(&JsValue::from(f));
-        }
+    /// Executes a query over the authorizer, expecting exactly one result
+    #[wasm_bindgen(js_name = queryOne)]
+    pub fn query_one(&mut self, rule: &Rule) -> Result<Fact, JsValue> {
+        let mut v = self.run_query(rule.0.clone())?;
+        let error = match v.len() {
+            1 => return Ok(Fact(v.remove(0), Live::new())),
+            0 => QueryError::NoResult,
+            count => QueryError::MultipleResults { count },
+        };
+        Err(serde_wasm_bindgen::to_value(&error).unwrap())
+    }
 
-        Ok(facts)
+    /// Executes a query over the authorizer, expecting exactly one result with one term
+    ///
+    /// Returns the term as a JS value: a string, `BigInt`, boolean, `Date`, `Uint8Array`
+    /// or an array for sets
+    #[wasm_bindgen(js_name = queryScalar)]
+    pub fn query_scalar(&mut self, rule: &Rule) -> Result<JsValue, JsValue> {
+        let fact = self.query_one(rule)?;
+        match fact.0.predicate.terms.as_slice() {
+            [term] => term_to_js(term),
+            terms => {
+                Err(
+                    serde_wasm_bindgen::to_value(&QueryError::NotScalar { terms: terms.len() })
+                        .unwrap(),
+                )
+            }
+        }
     }
 
     /// Executes a query over the authorizer
@@ -174,28 +473,467 @@ impl Authorizer {
         rule: &Rule,
         limits: JsValue,
     ) -> Result<js_sys::Array, JsValue> {
-        let limits: RunLimits = serde_wasm_bindgen::from_value(limits)?;
+        let limits = RunLimits::from_js(limits)?;
         let v: Vec<biscuit::builder::Fact> = self
-            .0
+            .inner
             .query_with_limits(rule.0.clone(), limits.to_rust_limits())
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
 
-        let facts = js_sys::Array::new();
-        for f in v.into_iter().map(Fact) {
-            facts.push(&JsValue::from(f));
-        }
+        Ok(facts_to_array(v))
+    }
+
+    /// Executes a query over the authorizer, on facts from all blocks regardless of
+    /// their scope
+    #[wasm_bindgen(js_name = queryAll)]
+    pub fn query_all(&mut self, rule: &Rule) -> Result<js_sys::Array, JsValue> {
+        let v: Vec<biscuit::builder::Fact> = self
+            .inner
+            .query_all(rule.0.clone())
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
 
-        Ok(facts)
+        Ok(facts_to_array(v))
+    }
+
+    /// Executes a query over the authorizer, trusting the provided scopes instead of
+    /// the ones of the rule
+    ///
+    /// Scopes can be `"authority"`, `"previous"`, or public keys (as `PublicKey` objects
+    /// or `ed25519/<hex>` strings)
+    #[wasm_bindgen(js_name = queryTrusting)]
+    pub fn query_trusting(
+        &mut self,
+        rule: &Rule,
+        scopes: &js_sys::Array,
+    ) -> Result<js_sys::Array, JsValue> {
+        let mut rule = rule.0.clone();
+        rule.scopes = scopes
+            .iter()
+            .map(js_to_scope)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(facts_to_array(self.run_query(rule)?))
     }
 
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self) -> String {
-        self.0.print_world()
+        self.inner.print_world()
+    }
+
+    /// Returns the contents of the authorizer as `{ facts, rules, checks, policies }`
+    ///
+    /// Each element contains its Datalog `code` and its `origins`, a list of
+    /// `{ type: "authorizer" }` or `{ type: "block", blockIndex, externalKey? }` objects
+    #[wasm_bindgen(js_name = dumpWorld)]
+    pub fn dump_world(&self) -> Result<JsValue, JsValue> {
+        let world = self.world().map_err(|e| {
+            serde_wasm_bindgen::to_value(&biscuit::error::Token::Format(e)).unwrap()
+        })?;
+        serde_wasm_bindgen::to_value(&world).map_err(|e| e.into())
+    }
+}
+
+impl Authorizer {
+    /// the execution statistics are only exposed through the authorizer's snapshot
+    fn snapshot(&self) -> Result<biscuit::format::schema::AuthorizerSnapshot, JsValue> {
+        self.inner
+            .snapshot()
+            .map_err(|e| serde_wasm_bindgen::to_value(&biscuit::error::Token::Format(e)).unwrap())
+    }
+
+    fn run_query(
+        &mut self,
+        rule: biscuit::builder::Rule,
+    ) -> Result<Vec<biscuit::builder::Fact>, JsValue> {
+        self.inner
+            .query(rule)
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+    }
+
+    pub(crate) fn from_token(inner: biscuit::Authorizer, token: &biscuit::Biscuit) -> Authorizer {
+        Authorizer {
+            token: Some(token.clone()),
+            external_keys: external_keys(token),
+            revocation_ids: revocation_ids(token),
+            ..Authorizer::from(inner)
+        }
+    }
+
+    fn check_revocation(&self) -> Result<(), JsValue> {
+        match &self.revocation_list {
+            Some(revoked) => check_revoked(revoked, &self.revocation_ids)
+                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap()),
+            None => Ok(()),
+        }
+    }
+
+    fn block_origin(&self, block_index: usize) -> Origin {
+        Origin::Block {
+            block_index,
+            external_key: self.external_keys.get(block_index).cloned().flatten(),
+        }
+    }
+
+    /// Builds the world from the authorizer's snapshot, which holds the facts with
+    /// their origins, and from the token's blocks
+    fn world(&self) -> Result<World, biscuit::error::Format> {
+        use biscuit::builder::Convert;
+        use biscuit::format::{
+            convert::{proto_snapshot_block_to_token_block, v2},
+            schema::origin::Content,
+        };
+
+        let snapshot = self.inner.snapshot()?.world;
+        let mut symbols = biscuit::datalog::SymbolTable::from(snapshot.symbols)?;
+        for key in snapshot.public_keys.iter() {
+            add_public_key(&mut symbols, biscuit::PublicKey::from_proto(key)?);
+        }
+
+        let mut facts: BTreeMap<String, Vec<Origin>> = BTreeMap::new();
+        let mut rules = Vec::new();
+        let mut checks = Vec::new();
+
+        // facts from the token are loaded when adding it, the authorizer's own facts
+        // and the generated ones are only in the world after authorization
+        for generated in snapshot.generated_facts.iter() {
+            let origins: Vec<Origin> = generated
+                .origins
+                .iter()
+                .map(|origin| match origin.content {
+                    Some(Content::Origin(i)) => self.block_origin(i as usize),
+                    _ => Origin::Authorizer,
+                })
+                .collect();
+            for fact in generated.facts.iter() {
+                let fact = v2::proto_fact_to_token_fact(fact)?;
+                let code = biscuit::builder::Fact::convert_from(&fact, &symbols)?.to_string();
+                add_fact(&mut facts, code, &origins);
+            }
+        }
+
+        // the snapshot translates every block with the symbols of the first party
+        // blocks, so the token's blocks are decoded from the token instead
+        if let Some(token) = &self.token {
+            self.add_token_blocks(token, &mut rules, &mut checks)?;
+        }
+
+        let block = proto_snapshot_block_to_token_block(&snapshot.authorizer_block)?;
+        for fact in block.facts.iter() {
+            let code = biscuit::builder::Fact::convert_from(fact, &symbols)?.to_string();
+            add_fact(&mut facts, code, &[Origin::Authorizer]);
+        }
+        for rule in block.rules.iter() {
+            rules.push(WorldElement {
+                code: biscuit::builder::Rule::convert_from(rule, &symbols)?.to_string(),
+                origins: vec![Origin::Authorizer],
+            });
+        }
+        for check in block.checks.iter() {
+            checks.push(WorldElement {
+                code: biscuit::builder::Check::convert_from(check, &symbols)?.to_string(),
+                origins: vec![Origin::Authorizer],
+            });
+        }
+
+        let version = snapshot.version.unwrap_or_default();
+        let policies = snapshot
+            .authorizer_policies
+            .iter()
+            .map(|policy| {
+                Ok(WorldElement {
+                    code: v2::proto_policy_to_policy(policy, &symbols, version)?.to_string(),
+                    origins: vec![Origin::Authorizer],
+                })
+            })
+            .collect::<Result<_, biscuit::error::Format>>()?;
+
+        Ok(World {
+            facts: facts
+                .into_iter()
+                .map(|(code, origins)| WorldElement { code, origins })
+                .collect(),
+            rules,
+            checks,
+            policies,
+        })
+    }
+
+    /// Lists the rules and checks of the token's blocks, each decoded with the symbols
+    /// it was written with: the symbols of all first party blocks, or its own symbols
+    /// for a third party block
+    fn add_token_blocks(
+        &self,
+        token: &biscuit::Biscuit,
+        rules: &mut Vec<WorldElement>,
+        checks: &mut Vec<WorldElement>,
+    ) -> Result<(), biscuit::error::Format> {
+        use biscuit::builder::Convert;
+        use biscuit::format::{convert::proto_block_to_token_block, schema};
+        use prost::Message;
+
+        let container = token.container().to_proto();
+        let mut token_symbols = biscuit::datalog::SymbolTable::new();
+        let mut public_keys = Vec::new();
+        let mut blocks = Vec::new();
+
+        for signed in std::iter::once(&container.authority).chain(container.blocks.iter()) {
+            let block = schema::Block::decode(&signed.block[..])
+                .map_err(|e| biscuit::error::Format::BlockDeserializationError(e.to_string()))?;
+            let external_key = signed
+                .external_signature
+                .as_ref()
+                .map(|signature| biscuit::PublicKey::from_proto(&signature.public_key))
+                .transpose()?;
+
+            public_keys.extend(external_key);
+            for key in block.public_keys.iter() {
+                public_keys.push(biscuit::PublicKey::from_proto(key)?);
+            }
+
+            let block = proto_block_to_token_block(&block, external_key)?;
+            if blocks.is_empty() || block.external_key.is_none() {
+                token_symbols.extend(&block.symbols)?;
+            }
+            blocks.push(block);
+        }
+
+        for key in public_keys.iter() {
+            add_public_key(&mut token_symbols, *key);
+        }
+
+        for (i, block) in blocks.iter().enumerate() {
+            let third_party_symbols;
+            let symbols = if i == 0 || block.external_key.is_none() {
+                &token_symbols
+            } else {
+                let mut symbols = block.symbols.clone();
+                for key in public_keys.iter() {
+                    add_public_key(&mut symbols, *key);
+                }
+                third_party_symbols = symbols;
+                &third_party_symbols
+            };
+
+            let origins = vec![self.block_origin(i)];
+            for rule in block.rules.iter() {
+                rules.push(WorldElement {
+                    code: biscuit::builder::Rule::convert_from(rule, symbols)?.to_string(),
+                    origins: origins.clone(),
+                });
+            }
+            for check in block.checks.iter() {
+                checks.push(WorldElement {
+                    code: biscuit::builder::Check::convert_from(check, symbols)?.to_string(),
+                    origins: origins.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn now(&self) -> Result<js_sys::Date, JsValue> {
+        match &self.clock {
+            Some(clock) => Ok(js_sys::Date::new(&clock.call0(&JsValue::NULL)?)),
+            None => Ok(js_sys::Date::new_0()),
+        }
+    }
+}
+
+impl From<biscuit::Authorizer> for Authorizer {
+    fn from(inner: biscuit::Authorizer) -> Self {
+        Authorizer {
+            inner,
+            clock: None,
+            has_time: false,
+            token: None,
+            external_keys: Vec::new(),
+            revocation_ids: Vec::new(),
+            revocation_list: None,
+            _live: Live::new(),
+        }
     }
 }
 
 impl Default for Authorizer {
     fn default() -> Self {
-        Self::new()
+        Authorizer::from(biscuit::Authorizer::new())
+    }
+}
+
+fn facts_to_array(v: Vec<biscuit::builder::Fact>) -> js_sys::Array {
+    let facts = js_sys::Array::new();
+    for f in v.into_iter().map(|f| Fact(f, Live::new())) {
+        facts.push(&JsValue::from(f));
+    }
+    facts
+}
+
+fn js_to_scope(value: JsValue) -> Result<biscuit::builder::Scope, JsValue> {
+    // `PublicKey` objects are converted with their `toDatalogParameter` method
+    let to_parameter = js_sys::Reflect::get(&value, &JsValue::from_str("toDatalogParameter"))
+        .ok()
+        .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+    let value = match to_parameter {
+        Some(f) => f.call0(&value)?,
+        None => value,
+    };
+
+    match value.as_string().as_deref() {
+        Some("authority") => Ok(biscuit::builder::Scope::Authority),
+        Some("previous") => Ok(biscuit::builder::Scope::Previous),
+        _ => {
+            let key: PublicKey = serde_wasm_bindgen::from_value(value)?;
+            Ok(biscuit::builder::Scope::PublicKey(key.0))
+        }
+    }
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Origin {
+    Authorizer,
+    #[serde(rename_all = "camelCase")]
+    Block {
+        block_index: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        external_key: Option<String>,
+    },
+}
+
+#[derive(Serialize)]
+pub struct WorldElement {
+    pub code: String,
+    pub origins: Vec<Origin>,
+}
+
+#[derive(Serialize)]
+pub struct World {
+    pub facts: Vec<WorldElement>,
+    pub rules: Vec<WorldElement>,
+    pub checks: Vec<WorldElement>,
+    pub policies: Vec<WorldElement>,
+}
+
+/// the same fact can be stored for several sets of origins, it is listed once
+/// with all of them
+fn add_fact(facts: &mut BTreeMap<String, Vec<Origin>>, code: String, origins: &[Origin]) {
+    let fact = facts.entry(code).or_default();
+    for origin in origins {
+        if !fact.contains(origin) {
+            fact.push(origin.clone());
+        }
+    }
+}
+
+fn log_result(result: &Result<usize, biscuit::error::Token>) {
+    match result {
+        Ok(index) => log::debug!("authorization succeeded with policy {}", index),
+        Err(e) => log::debug!("authorization failed: {}", e),
+    }
+}
+
+/// converting a scope is the only way to register a public key in a symbol table
+fn add_public_key(symbols: &mut biscuit::datalog::SymbolTable, key: biscuit::PublicKey) {
+    use biscuit::builder::Convert;
+    biscuit::builder::Scope::PublicKey(key).convert(symbols);
+}
+
+/// `serde_wasm_bindgen` only reads the known fields of a struct, so unknown keys
+/// (like a misspelled option) have to be rejected explicitly
+pub(crate) fn check_keys(value: &JsValue, allowed: &[&str]) -> Result<(), JsValue> {
+    if !value.is_object() {
+        return Ok(());
+    }
+
+    for key in js_sys::Object::keys(value.unchecked_ref()).iter() {
+        let key = key.as_string().unwrap_or_default();
+        if !allowed.contains(&key.as_str()) {
+            return Err(serde_wasm_bindgen::to_value(&format!(
+                "unknown field `{}`, expected one of: {}",
+                key,
+                allowed.join(", ")
+            ))
+            .unwrap());
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn external_keys(token: &biscuit::Biscuit) -> Vec<Option<String>> {
+    token
+        .external_public_keys()
+        .into_iter()
+        .map(|key| key.map(|k| format!("ed25519/{}", hex::encode(k.to_bytes()))))
+        .collect()
+}
+
+/// Facts, rules, checks and policies parsed once and reused across authorizers
+///
+/// Parsing and validating the authorizer code is done when creating the policy set,
+/// then each request gets its own authorizer with a copy of the policies. The set
+/// only holds authorizer code, never a token or the facts generated from it
+#[wasm_bindgen]
+pub struct PolicySet(biscuit::Authorizer, Live<PolicySet>);
+
+#[wasm_bindgen]
+impl PolicySet {
+    /// Parses facts, rules, checks and policies
+    #[wasm_bindgen(constructor)]
+    pub fn new(source: &str) -> Result<PolicySet, JsValue> {
+        let mut authorizer = biscuit::Authorizer::new();
+        authorizer
+            .add_code(source)
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        Ok(PolicySet(authorizer, Live::new()))
+    }
+
+    /// Returns a copy of the policy set
+    pub fn clone(&self) -> PolicySet {
+        PolicySet(self.0.clone(), Live::new())
+    }
+
+    /// Creates a policy set from the contents of an authorizer
+    #[wasm_bindgen(js_name = fromAuthorizer)]
+    pub fn from_authorizer(authorizer: &Authorizer) -> PolicySet {
+        // merging only takes the authorizer's facts, rules, checks and policies
+        let mut code = biscuit::Authorizer::new();
+        code.merge(authorizer.inner.clone());
+        PolicySet(code, Live::new())
+    }
+
+    /// Creates an authorizer containing the policy set
+    #[wasm_bindgen(js_name = createAuthorizer)]
+    pub fn create_authorizer(&self) -> Authorizer {
+        let mut authorizer = biscuit::Authorizer::new();
+        authorizer.merge(self.0.clone());
+        Authorizer::from(authorizer)
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_string(&self) -> String {
+        self.0.print_world()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_limits_defaults() {
+        let defaults = biscuit::datalog::RunLimits::default();
+
+        let limits: RunLimits = serde_json::from_str("{}").unwrap();
+        let limits = limits.to_rust_limits();
+        assert_eq!(limits.max_facts, defaults.max_facts);
+        assert_eq!(limits.max_iterations, defaults.max_iterations);
+        assert_eq!(limits.max_time, defaults.max_time);
+
+        let limits: RunLimits =
+            serde_json::from_str(r#"{ "max_facts": 10, "max_time_micro": 500 }"#).unwrap();
+        let limits = limits.to_rust_limits();
+        assert_eq!(limits.max_facts, 10);
+        assert_eq!(limits.max_iterations, defaults.max_iterations);
+        assert_eq!(limits.max_time, Duration::from_micros(500));
     }
 }
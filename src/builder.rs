@@ -2,35 +2,73 @@ use std::collections::{BTreeSet, HashMap};
 
 use biscuit_auth as biscuit;
 use js_sys::Array;
-use serde::{de::Visitor, Deserialize};
+use serde::{de::Visitor, Deserialize, Serialize};
 use time::OffsetDateTime;
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
 
-use crate::{make_rng, Biscuit, PrivateKey, PublicKey};
+use crate::memory::Live;
+use crate::{block_sizes, make_rng, Authorizer, Biscuit, PrivateKey, PublicKey};
 
 /// Creates a token
 #[wasm_bindgen]
-pub struct BiscuitBuilder(pub(crate) biscuit::builder::BiscuitBuilder);
+pub struct BiscuitBuilder(
+    pub(crate) biscuit::builder::BiscuitBuilder,
+    pub(crate) Live<BiscuitBuilder>,
+);
 
 #[wasm_bindgen]
 impl BiscuitBuilder {
     #[wasm_bindgen(constructor)]
     pub fn new() -> BiscuitBuilder {
-        BiscuitBuilder(biscuit::builder::BiscuitBuilder::new())
+        BiscuitBuilder(biscuit::builder::BiscuitBuilder::new(), Live::new())
     }
 
+    /// Creates a token signed with the root key
+    ///
+    /// The builder is not modified, so it can be used as a template for multiple tokens
     #[wasm_bindgen(js_name = build)]
-    pub fn build(self, root: &PrivateKey) -> Result<Biscuit, JsValue> {
+    pub fn build(&self, root: &PrivateKey) -> Result<Biscuit, JsValue> {
         let keypair = biscuit_auth::KeyPair::from(&root.0);
 
         let mut rng = make_rng();
         Ok(Biscuit(
             self.0
+                .clone()
+                .build_with_rng(&keypair, biscuit::datalog::SymbolTable::default(), &mut rng)
+                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            keypair.public(),
+            Live::new(),
+        ))
+    }
+
+    /// Creates a token, generating the next block's key pair from a seed
+    ///
+    /// Building twice with the same root key and seed produces the same token,
+    /// which is useful for tests. The key is generated with ChaCha20, so the output
+    /// does not change with the `rand` version. Do not use it to create production tokens
+    #[wasm_bindgen(js_name = buildWithRng)]
+    pub fn build_with_rng(&self, root: &PrivateKey, seed: f64) -> Result<Biscuit, JsValue> {
+        if seed.fract() != 0.0 || !(0.0..=u64::MAX as f64).contains(&seed) {
+            return Err(serde_wasm_bindgen::to_value("expecting a positive integer seed").unwrap());
+        }
+
+        let keypair = biscuit_auth::KeyPair::from(&root.0);
+        let mut rng: rand_chacha::ChaCha20Rng = rand::SeedableRng::seed_from_u64(seed as u64);
+        Ok(Biscuit(
+            self.0
+                .clone()
                 .build_with_rng(&keypair, biscuit::datalog::SymbolTable::default(), &mut rng)
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            keypair.public(),
+            Live::new(),
         ))
     }
 
+    /// Returns a copy of the builder
+    pub fn clone(&self) -> BiscuitBuilder {
+        BiscuitBuilder(self.0.clone(), Live::new())
+    }
+
     /// adds the content of an existing `BlockBuilder`
     pub fn merge(&mut self, other: &BlockBuilder) {
         self.0.merge(other.0.clone())
@@ -97,6 +135,25 @@ impl BiscuitBuilder {
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
+    /// Adds a check that the token expires at the given date
+    ///
+    /// This generates `check if time($time), $time <= <date>`, so the authorizer
+    /// must provide the current time in a `time` fact
+    #[wasm_bindgen(js_name = expiresAt)]
+    pub fn expires_at(&mut self, date: &js_sys::Date) -> Result<(), JsValue> {
+        self.0
+            .add_check(expiration_check(date_to_timestamp(date)?))
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+    }
+
+    /// Adds a check that the token expires after the given number of milliseconds
+    #[wasm_bindgen(js_name = expiresIn)]
+    pub fn expires_in(&mut self, milliseconds: f64) -> Result<(), JsValue> {
+        self.expires_at(&js_sys::Date::new(&JsValue::from_f64(
+            js_sys::Date::now() + milliseconds,
+        )))
+    }
+
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self) -> String {
         self.0.to_string()
@@ -111,7 +168,10 @@ impl Default for BiscuitBuilder {
 
 /// Creates a block to attenuate a token
 #[wasm_bindgen]
-pub struct BlockBuilder(pub(crate) biscuit::builder::BlockBuilder);
+pub struct BlockBuilder(
+    pub(crate) biscuit::builder::BlockBuilder,
+    pub(crate) Live<BlockBuilder>,
+);
 
 #[wasm_bindgen]
 impl BlockBuilder {
@@ -120,22 +180,62 @@ impl BlockBuilder {
     /// the builder can then be given to the token's append method to create an attenuated token
     #[wasm_bindgen(constructor)]
     pub fn new() -> BlockBuilder {
-        BlockBuilder(biscuit::builder::BlockBuilder::new())
+        BlockBuilder(biscuit::builder::BlockBuilder::new(), Live::new())
+    }
+
+    /// Returns a copy of the builder
+    pub fn clone(&self) -> BlockBuilder {
+        BlockBuilder(self.0.clone(), Live::new())
+    }
+
+    /// Creates a block builder from the facts, rules and checks of an authorizer
+    ///
+    /// Only the authorizer's own code is used: the token's content and the facts
+    /// generated during authorization are not carried over. Policies are dropped
+    /// as well, since blocks cannot contain them
+    #[wasm_bindgen(js_name = fromAuthorizer)]
+    pub fn from_authorizer(authorizer: &Authorizer) -> Result<BlockBuilder, JsValue> {
+        let code = authorizer
+            .inner
+            .save()
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        let mut builder = biscuit::builder::BlockBuilder::new();
+        for fact in code.facts {
+            builder
+                .add_fact(fact)
+                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        }
+        for rule in code.rules {
+            builder
+                .add_rule(rule)
+                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        }
+        for check in code.checks {
+            builder
+                .add_check(check)
+                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        }
+        Ok(BlockBuilder(builder, Live::new()))
+    }
+
+    /// adds the content of another `BlockBuilder`
+    pub fn merge(&mut self, other: &BlockBuilder) {
+        self.0.merge(other.0.clone())
     }
 
     /// Adds a Datalog fact
     #[wasm_bindgen(js_name = addFact)]
-    pub fn add_fact(&mut self, fact: Fact) -> Result<(), JsValue> {
+    pub fn add_fact(&mut self, fact: &Fact) -> Result<(), JsValue> {
         self.0
-            .add_fact(fact.0)
+            .add_fact(fact.0.clone())
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
     /// Adds a Datalog rule
     #[wasm_bindgen(js_name = addRule)]
-    pub fn add_rule(&mut self, rule: Rule) -> Result<(), JsValue> {
+    pub fn add_rule(&mut self, rule: &Rule) -> Result<(), JsValue> {
         self.0
-            .add_rule(rule.0)
+            .add_rule(rule.0.clone())
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
@@ -143,9 +243,9 @@ impl BlockBuilder {
     ///
     /// All checks, from authorizer and token, must be validated to authorize the request
     #[wasm_bindgen(js_name = addCheck)]
-    pub fn add_check(&mut self, check: Check) -> Result<(), JsValue> {
+    pub fn add_check(&mut self, check: &Check) -> Result<(), JsValue> {
         self.0
-            .add_check(check.0)
+            .add_check(check.0.clone())
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
@@ -184,12 +284,156 @@ impl BlockBuilder {
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
+    /// Adds a check that the token expires at the given date
+    ///
+    /// This generates `check if time($time), $time <= <date>`, so the authorizer
+    /// must provide the current time in a `time` fact
+    #[wasm_bindgen(js_name = expiresAt)]
+    pub fn expires_at(&mut self, date: &js_sys::Date) -> Result<(), JsValue> {
+        self.0
+            .add_check(expiration_check(date_to_timestamp(date)?))
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+    }
+
+    /// Adds a check that the token expires after the given number of milliseconds
+    #[wasm_bindgen(js_name = expiresIn)]
+    pub fn expires_in(&mut self, milliseconds: f64) -> Result<(), JsValue> {
+        self.expires_at(&js_sys::Date::new(&JsValue::from_f64(
+            js_sys::Date::now() + milliseconds,
+        )))
+    }
+
+    /// Estimates the serialized size of the block, in bytes
+    ///
+    /// The block is serialized as the authority block of a temporary token, so the
+    /// actual size can differ slightly depending on the symbols of the attenuated token
+    #[wasm_bindgen(js_name = estimateSize)]
+    pub fn estimate_size(&self) -> Result<usize, JsValue> {
+        let mut builder = biscuit::builder::BiscuitBuilder::new();
+        builder.merge(self.0.clone());
+
+        let mut rng = make_rng();
+        let keypair = biscuit::KeyPair::new_with_rng(&mut rng);
+        let token = builder
+            .build_with_rng(&keypair, biscuit::datalog::SymbolTable::default(), &mut rng)
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+
+        Ok(block_sizes(&token)[0])
+    }
+
+    /// Restricts the token to a list of operations
+    ///
+    /// This generates `check if operation($operation), [<operations>].contains($operation)`,
+    /// so the authorizer must provide the requested operation in an `operation` fact.
+    /// The list must contain at least one operation
+    #[wasm_bindgen(js_name = restrictOperations)]
+    pub fn restrict_operations(&mut self, operations: &Array) -> Result<(), JsValue> {
+        // the generated bindings do not check the argument's type, and a string
+        // would be iterated as a list of characters
+        if !Array::is_array(operations) {
+            return Err(serde_wasm_bindgen::to_value(&OperationsError::NotAnArray).unwrap());
+        }
+        let operations = operations
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| {
+                operation.as_string().ok_or_else(|| {
+                    serde_wasm_bindgen::to_value(&OperationsError::NotAString { index }).unwrap()
+                })
+            })
+            .collect::<Result<BTreeSet<String>, JsValue>>()?;
+        if operations.is_empty() {
+            return Err(serde_wasm_bindgen::to_value(&OperationsError::Empty).unwrap());
+        }
+
+        self.add_check_with_params(
+            "check if operation($operation), {operations}.contains($operation)",
+            &[(
+                "operations",
+                biscuit::builder::Term::Set(
+                    operations
+                        .into_iter()
+                        .map(biscuit::builder::Term::Str)
+                        .collect(),
+                ),
+            )],
+        )
+    }
+
+    /// Restricts the token to resources starting with a prefix
+    ///
+    /// This generates `check if resource($resource), $resource.starts_with(<prefix>)`,
+    /// so the authorizer must provide the requested resource in a `resource` fact
+    #[wasm_bindgen(js_name = restrictResourcePrefix)]
+    pub fn restrict_resource_prefix(&mut self, prefix: &str) -> Result<(), JsValue> {
+        self.add_check_with_params(
+            "check if resource($resource), $resource.starts_with({prefix})",
+            &[("prefix", biscuit::builder::Term::Str(prefix.to_string()))],
+        )
+    }
+
+    /// Restricts the token to one audience
+    ///
+    /// This generates `check if audience(<audience>)`, so the authorizer must
+    /// identify the service verifying the token in an `audience` fact
+    #[wasm_bindgen(js_name = restrictAudience)]
+    pub fn restrict_audience(&mut self, audience: &str) -> Result<(), JsValue> {
+        self.add_check_with_params(
+            "check if audience({audience})",
+            &[(
+                "audience",
+                biscuit::builder::Term::Str(audience.to_string()),
+            )],
+        )
+    }
+
+    /// Restricts the token to one operation on one resource
+    ///
+    /// This generates `check if resource(<resource>), operation(<operation>)`
+    #[wasm_bindgen(js_name = checkRight)]
+    pub fn check_right(&mut self, resource: &str, operation: &str) -> Result<(), JsValue> {
+        self.add_check_with_params(
+            "check if resource({resource}), operation({operation})",
+            &[
+                (
+                    "resource",
+                    biscuit::builder::Term::Str(resource.to_string()),
+                ),
+                (
+                    "operation",
+                    biscuit::builder::Term::Str(operation.to_string()),
+                ),
+            ],
+        )
+    }
+
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self) -> String {
         self.0.to_string()
     }
 }
 
+impl BlockBuilder {
+    fn add_check_with_params(
+        &mut self,
+        source: &str,
+        parameters: &[(&str, biscuit::builder::Term)],
+    ) -> Result<(), JsValue> {
+        let mut check: biscuit::builder::Check = source
+            .try_into()
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        for (name, value) in parameters {
+            check
+                .set(name, value.clone())
+                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
+        }
+
+        self.0
+            .add_check(check)
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+    }
+}
+
 impl Default for BlockBuilder {
     fn default() -> Self {
         Self::new()
@@ -197,27 +441,26 @@ impl Default for BlockBuilder {
 }
 
 #[wasm_bindgen]
-pub struct Fact(pub(crate) biscuit::builder::Fact);
+pub struct Fact(pub(crate) biscuit::builder::Fact, pub(crate) Live<Fact>);
 
 #[wasm_bindgen]
 impl Fact {
+    /// Returns a copy of the fact
+    pub fn clone(&self) -> Fact {
+        Fact(self.0.clone(), Live::new())
+    }
+
     #[wasm_bindgen(js_name = fromString)]
     pub fn from_str(source: &str) -> Result<Fact, JsValue> {
         source
             .try_into()
-            .map(Fact)
+            .map(|f| Fact(f, Live::new()))
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
     #[wasm_bindgen(js_name = unboundParameters)]
     pub fn unbound_parameters(&self) -> Array {
-        let array = Array::new();
-        for (k, v) in self.0.parameters.as_ref().unwrap_or(&HashMap::new()) {
-            if v.is_none() {
-                array.push(&JsValue::from_str(k));
-            }
-        }
-        array
+        names_to_array(unbound_names(std::iter::once(self.0.parameters.as_ref())))
     }
 
     #[wasm_bindgen(js_name = set)]
@@ -236,38 +479,33 @@ impl Fact {
 }
 
 #[wasm_bindgen]
-pub struct Rule(pub(crate) biscuit::builder::Rule);
+pub struct Rule(pub(crate) biscuit::builder::Rule, pub(crate) Live<Rule>);
 
 #[wasm_bindgen]
 impl Rule {
+    /// Returns a copy of the rule
+    pub fn clone(&self) -> Rule {
+        Rule(self.0.clone(), Live::new())
+    }
+
     #[wasm_bindgen(js_name = fromString)]
     pub fn from_str(source: &str) -> Result<Rule, JsValue> {
         source
             .try_into()
-            .map(Rule)
+            .map(|r| Rule(r, Live::new()))
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
     #[wasm_bindgen(js_name = unboundParameters)]
     pub fn unbound_parameters(&self) -> Array {
-        let array = Array::new();
-        for (k, v) in self.0.parameters.as_ref().unwrap_or(&HashMap::new()) {
-            if v.is_none() {
-                array.push(&JsValue::from_str(k));
-            }
-        }
-        array
+        names_to_array(unbound_names(std::iter::once(self.0.parameters.as_ref())))
     }
 
     #[wasm_bindgen(js_name = unboundScopeParameters)]
     pub fn unbound_scope_parameters(&self) -> Array {
-        let array = Array::new();
-        for (k, v) in self.0.scope_parameters.as_ref().unwrap_or(&HashMap::new()) {
-            if v.is_none() {
-                array.push(&JsValue::from_str(k));
-            }
-        }
-        array
+        names_to_array(unbound_names(std::iter::once(
+            self.0.scope_parameters.as_ref(),
+        )))
     }
 
     #[wasm_bindgen(js_name = set)]
@@ -295,48 +533,35 @@ impl Rule {
 }
 
 #[wasm_bindgen]
-pub struct Check(pub(crate) biscuit::builder::Check);
+pub struct Check(pub(crate) biscuit::builder::Check, pub(crate) Live<Check>);
 
 #[wasm_bindgen]
 impl Check {
+    /// Returns a copy of the check
+    pub fn clone(&self) -> Check {
+        Check(self.0.clone(), Live::new())
+    }
+
     #[wasm_bindgen(js_name = fromString)]
     pub fn from_str(source: &str) -> Result<Check, JsValue> {
         source
             .try_into()
-            .map(Check)
+            .map(|c| Check(c, Live::new()))
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
     #[wasm_bindgen(js_name = unboundParameters)]
     pub fn unbound_parameters(&self) -> Array {
-        let mut params = BTreeSet::new();
-        let array = Array::new();
-        for q in self.0.queries.iter() {
-            if let Some(ps) = q.parameters.as_ref() {
-                for (k, v) in ps {
-                    if v.is_none() && params.insert(k.to_string()) {
-                        array.push(&JsValue::from_str(k));
-                    }
-                }
-            }
-        }
-        array
+        names_to_array(unbound_names(
+            self.0.queries.iter().map(|q| q.parameters.as_ref()),
+        ))
     }
 
     #[wasm_bindgen(js_name = unboundScopeParameters)]
     pub fn unbound_scope_parameters(&self) -> Array {
-        let mut params = BTreeSet::new();
-        let array = Array::new();
-        for q in self.0.queries.iter() {
-            if let Some(ps) = q.scope_parameters.as_ref() {
-                for (k, v) in ps {
-                    if v.is_none() && params.insert(k.to_string()) {
-                        array.push(&JsValue::from_str(k));
-                    }
-                }
-            }
-        }
-        array
+        names_to_array(unbound_names(
+            self.0.queries.iter().map(|q| q.scope_parameters.as_ref()),
+        ))
     }
 
     #[wasm_bindgen(js_name = set)]
@@ -364,48 +589,35 @@ impl Check {
 }
 
 #[wasm_bindgen]
-pub struct Policy(pub(crate) biscuit::builder::Policy);
+pub struct Policy(pub(crate) biscuit::builder::Policy, pub(crate) Live<Policy>);
 
 #[wasm_bindgen]
 impl Policy {
+    /// Returns a copy of the policy
+    pub fn clone(&self) -> Policy {
+        Policy(self.0.clone(), Live::new())
+    }
+
     #[wasm_bindgen(js_name = fromString)]
     pub fn from_str(source: &str) -> Result<Policy, JsValue> {
         source
             .try_into()
-            .map(Policy)
+            .map(|p| Policy(p, Live::new()))
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
     #[wasm_bindgen(js_name = unboundParameters)]
     pub fn unbound_parameters(&self) -> Array {
-        let mut params = BTreeSet::new();
-        let array = Array::new();
-        for q in self.0.queries.iter() {
-            if let Some(ps) = q.parameters.as_ref() {
-                for (k, v) in ps {
-                    if v.is_none() && params.insert(k.to_string()) {
-                        array.push(&JsValue::from_str(k));
-                    }
-                }
-            }
-        }
-        array
+        names_to_array(unbound_names(
+            self.0.queries.iter().map(|q| q.parameters.as_ref()),
+        ))
     }
 
     #[wasm_bindgen(js_name = unboundScopeParameters)]
     pub fn unbound_scope_parameters(&self) -> Array {
-        let mut params = BTreeSet::new();
-        let array = Array::new();
-        for q in self.0.queries.iter() {
-            if let Some(ps) = q.scope_parameters.as_ref() {
-                for (k, v) in ps {
-                    if v.is_none() && params.insert(k.to_string()) {
-                        array.push(&JsValue::from_str(k));
-                    }
-                }
-            }
-        }
-        array
+        names_to_array(unbound_names(
+            self.0.queries.iter().map(|q| q.scope_parameters.as_ref()),
+        ))
     }
 
     #[wasm_bindgen(js_name = set)]
@@ -432,12 +644,224 @@ impl Policy {
     }
 }
 
+pub(crate) fn date_to_timestamp(date: &js_sys::Date) -> Result<u64, JsValue> {
+    let millis = date.get_time();
+    if !millis.is_finite() || millis < 0.0 {
+        return Err(
+            serde_wasm_bindgen::to_value("expecting a valid date after the unix epoch").unwrap(),
+        );
+    }
+
+    Ok((millis / 1000.0) as u64)
+}
+
+pub(crate) fn timestamp_to_date(timestamp: u64) -> js_sys::Date {
+    js_sys::Date::new(&JsValue::from_f64(timestamp as f64 * 1000.0))
+}
+
+fn expiration_check(timestamp: u64) -> biscuit::builder::Check {
+    let mut check: biscuit::builder::Check = "check if time($time), $time <= {expiration}"
+        .try_into()
+        .unwrap();
+    check
+        .set("expiration", biscuit::builder::Term::Date(timestamp))
+        .unwrap();
+    check
+}
+
+/// Returns the latest time accepted by a check, if all of its queries
+/// compare the `time` fact to a date
+pub(crate) fn check_expiration(check: &biscuit::builder::Check) -> Option<u64> {
+    let mut latest = None;
+    for query in check.queries.iter() {
+        let expiration = query_expiration(query)?;
+        latest = Some(latest.map_or(expiration, |l: u64| l.max(expiration)));
+    }
+    latest
+}
+
+fn query_expiration(query: &biscuit::builder::Rule) -> Option<u64> {
+    use biscuit::builder::{Op, Term};
+    use biscuit::datalog::Binary;
+
+    let time_variables: Vec<&str> = query
+        .body
+        .iter()
+        .filter(|p| p.name == "time")
+        .filter_map(|p| match p.terms.as_slice() {
+            [Term::Variable(v)] => Some(v.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    query
+        .expressions
+        .iter()
+        .filter_map(|e| match e.ops.as_slice() {
+            [Op::Value(Term::Variable(v)), Op::Value(Term::Date(d)), Op::Binary(op)]
+                if time_variables.contains(&v.as_str()) =>
+            {
+                match op {
+                    Binary::LessOrEqual => Some(*d),
+                    Binary::LessThan => d.checked_sub(1),
+                    _ => None,
+                }
+            }
+            [Op::Value(Term::Date(d)), Op::Value(Term::Variable(v)), Op::Binary(op)]
+                if time_variables.contains(&v.as_str()) =>
+            {
+                match op {
+                    Binary::GreaterOrEqual => Some(*d),
+                    Binary::GreaterThan => d.checked_sub(1),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .min()
+}
+
+/// names of the parameters without a value, de-duplicated across
+/// the parameter maps of a check or policy's queries
+fn unbound_names<'a, T: 'a>(
+    parameters: impl Iterator<Item = Option<&'a HashMap<String, Option<T>>>>,
+) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut names = Vec::new();
+    for ps in parameters.flatten() {
+        for (k, v) in ps {
+            if v.is_none() && seen.insert(k.as_str()) {
+                names.push(k.to_string());
+            }
+        }
+    }
+    names
+}
+
+fn names_to_array(names: Vec<String>) -> Array {
+    names.iter().map(JsValue::from).collect()
+}
+
 fn js_to_term(value: JsValue) -> Result<biscuit::builder::Term, JsValue> {
     serde_wasm_bindgen::from_value(value)
         .map(|t: Term| t.0)
         .map_err(|e| serde_wasm_bindgen::to_value(&e.to_string()).unwrap())
 }
 
+/// converts a JS value to a term, accepting `Date` and `Uint8Array` values
+/// in addition to the encodings produced by `prepareTerm`
+pub(crate) fn js_value_to_term(value: JsValue) -> Result<biscuit::builder::Term, JsValue> {
+    if let Some(date) = value.dyn_ref::<js_sys::Date>() {
+        Ok(biscuit::builder::Term::Date(date_to_timestamp(date)?))
+    } else if let Some(bytes) = value.dyn_ref::<js_sys::Uint8Array>() {
+        Ok(biscuit::builder::Term::Bytes(bytes.to_vec()))
+    } else {
+        js_to_term(value)
+    }
+}
+
+/// converts a term to a JS value, the reverse of `js_value_to_term`
+pub(crate) fn term_to_js(term: &biscuit::builder::Term) -> Result<JsValue, JsValue> {
+    use biscuit::builder::Term;
+
+    match term {
+        Term::Str(s) => Ok(JsValue::from_str(s)),
+        // Datalog integers are 64 bits, they would lose precision as JS numbers
+        Term::Integer(i) => Ok(js_sys::BigInt::from(*i).into()),
+        Term::Bool(b) => Ok(JsValue::from_bool(*b)),
+        Term::Date(d) => Ok(timestamp_to_date(*d).into()),
+        Term::Bytes(b) => Ok(js_sys::Uint8Array::from(b.as_slice()).into()),
+        Term::Set(set) => set
+            .iter()
+            .map(term_to_js)
+            .collect::<Result<Array, _>>()
+            .map(JsValue::from),
+        Term::Variable(_) | Term::Parameter(_) => Err(serde_wasm_bindgen::to_value(&format!(
+            "cannot convert {} to a JS value",
+            term
+        ))
+        .unwrap()),
+    }
+}
+
+fn is_plain_object(value: &JsValue) -> bool {
+    value.is_object()
+        && !Array::is_array(value)
+        && !value.is_instance_of::<js_sys::Date>()
+        && !value.is_instance_of::<js_sys::Uint8Array>()
+}
+
+/// Error returned by `restrictOperations` when the operations are not a non-empty
+/// array of strings
+#[derive(Serialize)]
+pub enum OperationsError {
+    NotAnArray,
+    NotAString { index: usize },
+    Empty,
+}
+
+/// Error returned when a fact cannot be named after a property
+///
+/// `Invalid` is returned when the name does not follow the Datalog grammar, and
+/// `NestedObject` when the property is an object that is not flattened
+#[derive(Serialize)]
+pub enum FactNameError {
+    Invalid { name: String },
+    NestedObject { name: String },
+}
+
+/// checks that a fact name is a letter followed by letters, digits, `_` or `:`
+pub(crate) fn check_fact_name(name: &str) -> Result<(), JsValue> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':');
+    if !valid {
+        return Err(serde_wasm_bindgen::to_value(&FactNameError::Invalid {
+            name: name.to_string(),
+        })
+        .unwrap());
+    }
+    Ok(())
+}
+
+/// generates one fact per property of the object, named after the property.
+/// Nested objects are flattened to `<property>_<nested property>` facts if
+/// `flatten` is set
+pub(crate) fn object_to_facts(
+    prefix: Option<&str>,
+    object: &js_sys::Object,
+    flatten: bool,
+    facts: &mut Vec<biscuit::builder::Fact>,
+) -> Result<(), JsValue> {
+    for entry in js_sys::Object::entries(object).iter() {
+        let entry: Array = entry.unchecked_into();
+        let key = entry.get(0).as_string().unwrap_or_default();
+        let name = match prefix {
+            Some(prefix) => format!("{}_{}", prefix, key),
+            None => key,
+        };
+
+        let value = entry.get(1);
+        if is_plain_object(&value) && flatten {
+            object_to_facts(Some(&name), value.unchecked_ref(), flatten, facts)?;
+            continue;
+        }
+
+        check_fact_name(&name)?;
+        if is_plain_object(&value) {
+            // objects are only accepted if they encode a term, like `{ date: "…" }`
+            let term = js_to_term(value).map_err(|_| {
+                serde_wasm_bindgen::to_value(&FactNameError::NestedObject { name: name.clone() })
+                    .unwrap()
+            })?;
+            facts.push(biscuit::builder::fact(&name, &[term]));
+        } else {
+            facts.push(biscuit::builder::fact(&name, &[js_value_to_term(value)?]));
+        }
+    }
+    Ok(())
+}
+
 pub struct Term(pub(crate) biscuit::builder::Term);
 
 impl<'de> Deserialize<'de> for Term {
@@ -471,6 +895,16 @@ impl<'de> Visitor<'de> for TermVisitor {
         Ok(Term(biscuit::builder::int(value)))
     }
 
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let value = value
+            .try_into()
+            .map_err(|_| E::custom("integer is out of range of i64"))?;
+        Ok(Term(biscuit::builder::int(value)))
+    }
+
     fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
     where
         E: serde::de::Error,
@@ -532,3 +966,67 @@ impl<'de> Visitor<'de> for TermVisitor {
         Ok(Term(biscuit::builder::Term::Set(set)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(json: &str) -> biscuit::builder::Term {
+        serde_json::from_str::<Term>(json).unwrap().0
+    }
+
+    #[test]
+    fn term_deserialization() {
+        assert_eq!(term("true"), biscuit::builder::boolean(true));
+        assert_eq!(term("1234"), biscuit::builder::int(1234));
+        assert_eq!(
+            term(r#""1234""#),
+            biscuit::builder::Term::Str("1234".to_string())
+        );
+        assert_eq!(
+            term(r#"{ "date": "2023-03-28T14:31:06Z" }"#),
+            biscuit::builder::Term::Date(1680013866)
+        );
+        assert_eq!(
+            term(r#"{ "bytes": "00aabb" }"#),
+            biscuit::builder::Term::Bytes(vec![0, 170, 187])
+        );
+        assert_eq!(
+            term(r#"["a", 12, true]"#),
+            biscuit::builder::Term::Set(
+                [
+                    biscuit::builder::Term::Str("a".to_string()),
+                    biscuit::builder::int(12),
+                    biscuit::builder::boolean(true),
+                ]
+                .into_iter()
+                .collect()
+            )
+        );
+
+        assert!(serde_json::from_str::<Term>(r#"{ "date": "yesterday" }"#).is_err());
+        assert!(serde_json::from_str::<Term>(r#"{ "bytes": "xyz" }"#).is_err());
+        assert!(serde_json::from_str::<Term>(r#"{ "other": "1234" }"#).is_err());
+    }
+
+    #[test]
+    fn unbound_parameters_are_deduplicated() {
+        let check: biscuit::builder::Check =
+            "check if fact({a}, {b}) or other({a}) trusting {key}, {key}"
+                .try_into()
+                .unwrap();
+
+        let mut names = unbound_names(check.queries.iter().map(|q| q.parameters.as_ref()));
+        names.sort();
+        assert_eq!(names, vec!["a".to_string(), "b".to_string()]);
+
+        let scope_names = unbound_names(check.queries.iter().map(|q| q.scope_parameters.as_ref()));
+        assert_eq!(scope_names, vec!["key".to_string()]);
+
+        let mut policy: biscuit::builder::Policy =
+            "allow if fact({a}) or other({a}, {b})".try_into().unwrap();
+        policy.set("b", 1234i64).unwrap();
+        let names = unbound_names(policy.queries.iter().map(|q| q.parameters.as_ref()));
+        assert_eq!(names, vec!["a".to_string()]);
+    }
+}
@@ -1,32 +1,60 @@
 use biscuit_auth as biscuit;
 use serde::{de::Visitor, Deserialize};
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::memory::Live;
 
 /// A pair of public and private key
+///
+/// The private key is wiped from memory when the key pair is freed
 #[wasm_bindgen]
-pub struct KeyPair(pub(crate) biscuit::KeyPair);
+pub struct KeyPair(pub(crate) Box<biscuit::KeyPair>, pub(crate) Live<KeyPair>);
 
 #[wasm_bindgen]
 impl KeyPair {
     #[wasm_bindgen(constructor)]
     pub fn new() -> KeyPair {
-        let mut rng = make_rng();
-        KeyPair(biscuit::KeyPair::new_with_rng(&mut rng))
+        wiping_stack(|| {
+            let mut rng = make_rng();
+            KeyPair(
+                Box::new(biscuit::KeyPair::new_with_rng(&mut rng)),
+                Live::new(),
+            )
+        })
     }
 
     #[wasm_bindgen(js_name = fromPrivateKey)]
     pub fn from(key: &PrivateKey) -> Self {
-        KeyPair(biscuit::KeyPair::from(&key.0))
+        wiping_stack(|| KeyPair(Box::new(biscuit::KeyPair::from(&key.0)), Live::new()))
     }
 
     #[wasm_bindgen(js_name = getPublicKey)]
     pub fn public(&self) -> PublicKey {
-        PublicKey(self.0.public())
+        PublicKey(self.0.public(), Live::new())
     }
 
     #[wasm_bindgen(js_name = getPrivateKey)]
     pub fn private(&self) -> PrivateKey {
-        PrivateKey(self.0.private())
+        wiping_stack(|| PrivateKey(Box::new(self.0.private()), Live::new()))
+    }
+
+    /// Returns a copy of the key pair
+    pub fn clone(&self) -> KeyPair {
+        wiping_stack(|| {
+            KeyPair(
+                Box::new(biscuit::KeyPair::from(&self.0.private())),
+                Live::new(),
+            )
+        })
+    }
+
+    /// Wipes the private key from memory
+    ///
+    /// The key pair is replaced in place by one derived from an all-zero
+    /// private key, it must not be used afterwards and should still be freed
+    pub fn dispose(&mut self) {
+        wiping_stack(|| *self.0 = biscuit::KeyPair::from(&zero_private_key()))
     }
 }
 
@@ -38,10 +66,15 @@ impl Default for KeyPair {
 
 /// Public key
 #[wasm_bindgen]
-pub struct PublicKey(pub(crate) biscuit::PublicKey);
+pub struct PublicKey(pub(crate) biscuit::PublicKey, pub(crate) Live<PublicKey>);
 
 #[wasm_bindgen]
 impl PublicKey {
+    /// Returns a copy of the key
+    pub fn clone(&self) -> PublicKey {
+        PublicKey(self.0, Live::new())
+    }
+
     /// Serializes a public key to raw bytes
     #[wasm_bindgen(js_name = toBytes)]
     pub fn to_bytes(&self, out: &mut [u8]) -> Result<(), JsValue> {
@@ -73,7 +106,7 @@ impl PublicKey {
     pub fn from_bytes(data: &[u8]) -> Result<PublicKey, JsValue> {
         let key = biscuit_auth::PublicKey::from_bytes(data)
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
-        Ok(PublicKey(key))
+        Ok(PublicKey(key, Live::new()))
     }
 
     /// Deserializes a public key from a hexadecimal string
@@ -90,7 +123,7 @@ impl PublicKey {
         })?;
         let key = biscuit_auth::PublicKey::from_bytes(&data)
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
-        Ok(PublicKey(key))
+        Ok(PublicKey(key, Live::new()))
     }
 }
 
@@ -128,50 +161,85 @@ impl<'de> Visitor<'de> for PublicKeyVisitor {
                 "expected a public key of the format `ed25519/<hex>`".to_string(),
             )),
             Some(s) => match biscuit::PublicKey::from_bytes_hex(s) {
-                Ok(pk) => Ok(PublicKey(pk)),
+                Ok(pk) => Ok(PublicKey(pk, Live::new())),
                 Err(e) => Err(E::custom(format!("could not parse public key: {}", e))),
             },
         }
     }
 }
 
+/// Private key
+///
+/// The key material is wiped from memory when the key is freed, intermediate
+/// copies made while serializing or deserializing are wiped as well
 #[wasm_bindgen]
-pub struct PrivateKey(pub(crate) biscuit::PrivateKey);
+pub struct PrivateKey(
+    pub(crate) Box<biscuit::PrivateKey>,
+    pub(crate) Live<PrivateKey>,
+);
 
 #[wasm_bindgen]
 impl PrivateKey {
+    /// Returns a copy of the key
+    pub fn clone(&self) -> PrivateKey {
+        wiping_stack(|| {
+            PrivateKey(
+                Box::new(biscuit::KeyPair::from(&self.0).private()),
+                Live::new(),
+            )
+        })
+    }
+
     /// Serializes a private key to raw bytes
+    ///
+    /// The bytes are written directly to the JS array, without going through
+    /// a copy in the wasm memory
     #[wasm_bindgen(js_name = toBytes)]
-    pub fn to_bytes(&self, out: &mut [u8]) -> Result<(), JsValue> {
-        if out.len() != 32 {
+    pub fn to_bytes(&self, out: &js_sys::Uint8Array) -> Result<(), JsValue> {
+        if out.length() != 32 {
             return Err(serde_wasm_bindgen::to_value(&biscuit::error::Token::Format(
-                biscuit::error::Format::InvalidKeySize(out.len()),
+                biscuit::error::Format::InvalidKeySize(out.length() as usize),
             ))
             .unwrap());
         }
 
-        out.copy_from_slice(&self.0.to_bytes());
+        wiping_stack(|| out.copy_from(&Zeroizing::new(self.0.to_bytes())[..]));
         Ok(())
     }
 
     /// Serializes a private key to a hexadecimal string
+    ///
+    /// The string is created on the JS side, the copy used to encode it in the
+    /// wasm memory is wiped afterwards
     #[wasm_bindgen(js_name = toString)]
-    pub fn to_hex(&self) -> String {
-        hex::encode(self.0.to_bytes())
+    pub fn to_hex(&self) -> js_sys::JsString {
+        let hex = self.hex();
+        js_sys::JsString::from(std::str::from_utf8(&hex).unwrap())
+    }
+
+    /// Wipes the private key from memory
+    ///
+    /// The key is replaced in place by an all-zero key, it must not be used
+    /// afterwards and should still be freed
+    pub fn dispose(&mut self) {
+        wiping_stack(|| *self.0 = zero_private_key())
     }
 
     /// Deserializes a private key from raw bytes
+    ///
+    /// The copy of the input made in the wasm memory is wiped afterwards
     #[wasm_bindgen(js_name = fromBytes)]
-    pub fn from_bytes(data: &[u8]) -> Result<PrivateKey, JsValue> {
-        let key = biscuit_auth::PrivateKey::from_bytes(data)
-            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
-        Ok(PrivateKey(key))
+    pub fn from_bytes(data: Vec<u8>) -> Result<PrivateKey, JsValue> {
+        let data = Zeroizing::new(data);
+        wiping_stack(|| biscuit_auth::PrivateKey::from_bytes(&data).map(Box::new))
+            .map(|key| PrivateKey(key, Live::new()))
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 
     /// Deserializes a private key from a hexadecimal string
     #[wasm_bindgen(js_name = fromString)]
     pub fn from_hex(data: &str) -> Result<PrivateKey, JsValue> {
-        let data = hex::decode(data).map_err(|e| {
+        let data = hex::decode(data).map(Zeroizing::new).map_err(|e| {
             serde_wasm_bindgen::to_value(&biscuit::error::Token::Format(
                 biscuit::error::Format::InvalidKey(format!(
                     "could not deserialize hex encoded key: {}",
@@ -180,14 +248,83 @@ impl PrivateKey {
             ))
             .unwrap()
         })?;
-        let key = biscuit_auth::PrivateKey::from_bytes(&data)
-            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?;
-        Ok(PrivateKey(key))
+        wiping_stack(|| biscuit_auth::PrivateKey::from_bytes(&data).map(Box::new))
+            .map(|key| PrivateKey(key, Live::new()))
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 }
 
+impl PrivateKey {
+    /// hexadecimal encoding of the key, in a buffer wiped when dropped
+    fn hex(&self) -> Zeroizing<Vec<u8>> {
+        let mut hex = Zeroizing::new(vec![0u8; 64]);
+        wiping_stack(|| {
+            hex::encode_to_slice(&Zeroizing::new(self.0.to_bytes())[..], &mut hex[..]).unwrap()
+        });
+        hex
+    }
+}
+
+fn zero_private_key() -> biscuit::PrivateKey {
+    biscuit::PrivateKey::from_bytes(&[0u8; 32]).unwrap()
+}
+
+/// Runs `f`, then overwrites the stack space it used
+///
+/// biscuit-auth and ed25519-dalek copy the key bytes to stack arrays that
+/// are not wiped, and moving a key leaves copies behind. Keys are boxed in
+/// `f`, so that only pointers are moved out of it
+fn wiping_stack<T>(f: impl FnOnce() -> T) -> T {
+    #[inline(never)]
+    fn run<T>(f: impl FnOnce() -> T) -> T {
+        f()
+    }
+
+    #[inline(never)]
+    fn wipe() {
+        let mut buf = [0u8; 16 * 1024];
+        buf.zeroize();
+        std::hint::black_box(&buf);
+    }
+
+    let result = run(f);
+    wipe();
+    result
+}
+
 pub(crate) fn make_rng() -> rand::rngs::StdRng {
     let mut data = [0u8; 8];
     getrandom::getrandom(&mut data[..]).unwrap();
     rand::SeedableRng::seed_from_u64(u64::from_le_bytes(data))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "acdd6d5b53bfee478bf689f8e012fe7988bf755e3d7c5152947abc149bc20189";
+
+    #[test]
+    fn keys_hex_roundtrip() {
+        let public_key = PublicKey::from_hex(PUBLIC_KEY).unwrap();
+        assert_eq!(public_key.to_hex(), PUBLIC_KEY);
+        assert_eq!(
+            public_key.to_datalog_parameter(),
+            format!("ed25519/{}", PUBLIC_KEY)
+        );
+
+        let keypair = biscuit::KeyPair::new();
+        let private_key = PrivateKey(Box::new(keypair.private()), Live::new());
+        let hex = String::from_utf8(private_key.hex().to_vec()).unwrap();
+        assert_eq!(*PrivateKey::from_hex(&hex).unwrap().hex(), hex.as_bytes());
+    }
+
+    #[test]
+    fn public_key_deserialization() {
+        let key: PublicKey = serde_json::from_str(&format!(r#""ed25519/{}""#, PUBLIC_KEY)).unwrap();
+        assert_eq!(key.to_hex(), PUBLIC_KEY);
+
+        assert!(serde_json::from_str::<PublicKey>(&format!(r#""{}""#, PUBLIC_KEY)).is_err());
+        assert!(serde_json::from_str::<PublicKey>(r#""ed25519/abcd""#).is_err());
+    }
+}
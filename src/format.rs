@@ -0,0 +1,62 @@
+use biscuit_auth as biscuit;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+use crate::check_keys;
+
+/// Error returned when appending a block makes the token exceed its size budget
+#[derive(Serialize)]
+pub enum SizeError {
+    #[serde(rename_all = "camelCase")]
+    TooLarge { size: usize, max_total_size: usize },
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AppendOptions {
+    pub max_total_size: Option<usize>,
+}
+
+impl AppendOptions {
+    const FIELDS: &'static [&'static str] = &["maxTotalSize"];
+
+    /// Parses append options from a JS object, rejecting unknown keys
+    pub fn from_js(value: JsValue) -> Result<AppendOptions, JsValue> {
+        check_keys(&value, AppendOptions::FIELDS)?;
+        let options: Option<AppendOptions> = serde_wasm_bindgen::from_value(value)?;
+        Ok(options.unwrap_or_default())
+    }
+
+    pub(crate) fn check_size(&self, token: &biscuit::Biscuit) -> Result<(), JsValue> {
+        if let Some(max_total_size) = self.max_total_size {
+            let size = token_size(token)?;
+            if size > max_total_size {
+                return Err(serde_wasm_bindgen::to_value(&SizeError::TooLarge {
+                    size,
+                    max_total_size,
+                })
+                .unwrap());
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn token_size(token: &biscuit::Biscuit) -> Result<usize, JsValue> {
+    token
+        .serialized_size()
+        .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+}
+
+/// Returns the serialized size of each block, starting with the authority block
+pub(crate) fn block_sizes(token: &biscuit::Biscuit) -> Vec<usize> {
+    // the protobuf container gives the encoded length of each block without
+    // serializing the token
+    let container = token.container().to_proto();
+
+    std::iter::once(&container.authority)
+        .chain(container.blocks.iter())
+        .map(|block| block.encoded_len())
+        .collect()
+}
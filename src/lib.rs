@@ -2,16 +2,28 @@
 // so that we can expose them to JS with a proper name.
 #![allow(clippy::inherent_to_string, clippy::should_implement_trait)]
 use biscuit_auth as biscuit;
+use memory::Live;
 use wasm_bindgen::prelude::*;
 
 mod authorizer;
 mod builder;
 mod crypto;
+mod format;
+mod logger;
+mod memory;
+mod parser;
+mod revocation;
 
 pub use authorizer::*;
 pub use builder::*;
 pub use crypto::*;
+pub use format::*;
+pub use logger::*;
+pub use memory::*;
+pub use parser::*;
+pub use revocation::*;
 
+#[cfg(all(target_arch = "wasm32", feature = "wee-alloc"))]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
@@ -20,7 +32,12 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 /// it can produce an attenuated or sealed token, or be used
 /// in an authorizer along with Datalog policies
 #[wasm_bindgen]
-pub struct Biscuit(biscuit::Biscuit);
+pub struct Biscuit(
+    biscuit::Biscuit,
+    /// root public key, used to reload the token after appending a third party block
+    biscuit::PublicKey,
+    Live<Biscuit>,
+);
 
 #[wasm_bindgen]
 impl Biscuit {
@@ -31,6 +48,11 @@ impl Biscuit {
         BiscuitBuilder::new()
     }
 
+    /// Returns a copy of the token
+    pub fn clone(&self) -> Biscuit {
+        Biscuit(self.0.clone(), self.1, Live::new())
+    }
+
     /// Creates a BlockBuilder
     ///
     /// the builder can be merged with a `BiscuitBuilder`, another `BlockBuilder`,
@@ -40,23 +62,50 @@ impl Biscuit {
     }
 
     /// Creates an attenuated token by adding the block generated by the BlockBuilder
+    ///
+    /// The options can contain a `maxTotalSize` in bytes, making the append fail if the
+    /// attenuated token would exceed it
     #[wasm_bindgen(js_name = appendBlock)]
-    pub fn append(&self, block: &BlockBuilder) -> Result<Biscuit, JsValue> {
+    pub fn append(&self, block: &BlockBuilder, options: JsValue) -> Result<Biscuit, JsValue> {
+        let options = AppendOptions::from_js(options)?;
         let keypair = KeyPair::new();
+        let token = self
+            .0
+            .append_with_keypair(&keypair.0, block.0.clone())
+            .map_err(append_error)?;
+
+        options.check_size(&token)?;
+        Ok(Biscuit(token, self.1, Live::new()))
+    }
+
+    /// Creates an attenuated token by adding the block generated by the BlockBuilder,
+    /// using the provided key pair for the next block instead of a random one
+    ///
+    /// This produces reproducible tokens for tests. The key pair must not be reused
+    /// for production tokens
+    #[wasm_bindgen(js_name = appendBlockWithKeyPair)]
+    pub fn append_with_keypair(
+        &self,
+        block: &BlockBuilder,
+        keypair: &KeyPair,
+    ) -> Result<Biscuit, JsValue> {
         Ok(Biscuit(
             self.0
                 .append_with_keypair(&keypair.0, block.0.clone())
-                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+                .map_err(append_error)?,
+            self.1,
+            Live::new(),
         ))
     }
 
     /// Creates an authorizer from the token
     #[wasm_bindgen(js_name = getAuthorizer)]
     pub fn authorizer(&self) -> Result<Authorizer, JsValue> {
-        Ok(Authorizer(
+        Ok(Authorizer::from_token(
             self.0
                 .authorizer()
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            &self.0,
         ))
     }
 
@@ -69,9 +118,20 @@ impl Biscuit {
             self.0
                 .seal()
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            self.1,
+            Live::new(),
         ))
     }
 
+    /// Returns true if the token is sealed
+    ///
+    /// A sealed token cannot be attenuated: `appendBlock` and `appendThirdPartyBlock` fail
+    /// with an `AppendOnSealed` error
+    #[wasm_bindgen(js_name = isSealed)]
+    pub fn is_sealed(&self) -> bool {
+        self.0.container().proof.is_sealed()
+    }
+
     /// Deserializes a token from raw data
     ///
     /// This will check the signature using the root key
@@ -80,6 +140,8 @@ impl Biscuit {
         Ok(Biscuit(
             biscuit::Biscuit::from(data, root.0)
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            root.0,
+            Live::new(),
         ))
     }
 
@@ -91,6 +153,8 @@ impl Biscuit {
         Ok(Biscuit(
             biscuit::Biscuit::from_base64(data, root.0)
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            root.0,
+            Live::new(),
         ))
     }
 
@@ -124,6 +188,74 @@ impl Biscuit {
         ids.into_boxed_slice()
     }
 
+    /// Returns the earliest expiration date set by the checks of all blocks
+    ///
+    /// This recognizes checks like `check if time($time), $time <= <date>`, and
+    /// returns `undefined` if the token does not expire
+    #[wasm_bindgen(js_name = getExpiration)]
+    pub fn expiration(&self) -> Result<Option<js_sys::Date>, JsValue> {
+        let (_, _, checks, _) = self
+            .0
+            .authorizer()
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?
+            .dump();
+
+        Ok(checks
+            .iter()
+            .filter_map(check_expiration)
+            .min()
+            .map(timestamp_to_date))
+    }
+
+    /// Returns true if one of the token's revocation identifiers is in the list
+    #[wasm_bindgen(js_name = isRevoked)]
+    pub fn is_revoked(&self, list: &RevocationList) -> bool {
+        check_revoked(&list.0, &revocation_ids(&self.0)).is_err()
+    }
+
+    /// Returns the list of revocation identifiers, as raw bytes
+    #[wasm_bindgen(js_name = getRevocationIdentifiersBytes)]
+    pub fn revocation_identifiers_bytes(&self) -> js_sys::Array {
+        self.0
+            .revocation_identifiers()
+            .into_iter()
+            .map(|i| JsValue::from(js_sys::Uint8Array::from(i.as_slice())))
+            .collect()
+    }
+
+    /// Returns the revocation identifier of each block, as `{ blockIndex, id, externalKey? }`
+    ///
+    /// `id` is encoded as a hexadecimal string, `externalKey` is set for third party blocks
+    #[wasm_bindgen(js_name = getRevocationInfo)]
+    pub fn revocation_info(&self) -> Result<JsValue, JsValue> {
+        let external_keys = external_keys(&self.0);
+        let info: Vec<RevocationInfo> = revocation_ids(&self.0)
+            .into_iter()
+            .enumerate()
+            .map(|(block_index, id)| RevocationInfo {
+                block_index,
+                id,
+                external_key: external_keys.get(block_index).cloned().flatten(),
+            })
+            .collect();
+
+        serde_wasm_bindgen::to_value(&info).map_err(|e| e.into())
+    }
+
+    /// Returns the size of the serialized token, in bytes
+    pub fn size(&self) -> Result<usize, JsValue> {
+        token_size(&self.0)
+    }
+
+    /// Returns the size of each serialized block, in bytes, starting with the authority block
+    #[wasm_bindgen(js_name = getBlockSizes)]
+    pub fn block_sizes(&self) -> js_sys::Array {
+        block_sizes(&self.0)
+            .into_iter()
+            .map(|size| JsValue::from(size as u32))
+            .collect()
+    }
+
     /// Returns the number of blocks in the token
     #[wasm_bindgen(js_name = countBlocks)]
     pub fn block_count(&self) -> usize {
@@ -145,6 +277,7 @@ impl Biscuit {
             self.0
                 .third_party_request()
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            Live::new(),
         ))
     }
 
@@ -156,11 +289,16 @@ impl Biscuit {
         block: &ThirdPartyBlock,
     ) -> Result<Biscuit, JsValue> {
         let next_keypair = KeyPair::new();
-        Ok(Biscuit(
-            self.0
-                .append_third_party_with_keypair(external_key.0, block.0.clone(), next_keypair.0)
-                .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
-        ))
+        let token = self
+            .0
+            .append_third_party_with_keypair(external_key.0, block.0.clone(), *next_keypair.0)
+            .and_then(|token| token.to_vec())
+            // appending does not register the external key of the new block, so
+            // rules and queries trusting that key would not see its facts: loading
+            // the token again rebuilds the mapping from keys to blocks
+            .and_then(|data| biscuit::Biscuit::from(data, self.1))
+            .map_err(append_error)?;
+        Ok(Biscuit(token, self.1, Live::new()))
     }
 
     #[wasm_bindgen(js_name = toString)]
@@ -169,18 +307,34 @@ impl Biscuit {
     }
 }
 
+/// Appending to a sealed token fails with `AlreadySealed`, the error used when sealing
+/// twice: report it as `AppendOnSealed` instead
+fn append_error(e: biscuit::error::Token) -> JsValue {
+    let e = match e {
+        biscuit::error::Token::AlreadySealed => biscuit::error::Token::AppendOnSealed,
+        e => e,
+    };
+    serde_wasm_bindgen::to_value(&e).unwrap()
+}
+
 /// Creates a block to attenuate a token
 #[wasm_bindgen]
-pub struct ThirdPartyRequest(biscuit::ThirdPartyRequest);
+pub struct ThirdPartyRequest(biscuit::ThirdPartyRequest, Live<ThirdPartyRequest>);
 
 #[wasm_bindgen]
 impl ThirdPartyRequest {
+    /// Returns a copy of the request
+    pub fn clone(&self) -> Result<ThirdPartyRequest, JsValue> {
+        Ok(ThirdPartyRequest(self.copy()?, Live::new()))
+    }
+
     /// Deserializes a third party request from raw data
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<ThirdPartyRequest, JsValue> {
         Ok(ThirdPartyRequest(
             biscuit::ThirdPartyRequest::deserialize(data)
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            Live::new(),
         ))
     }
 
@@ -192,6 +346,7 @@ impl ThirdPartyRequest {
         Ok(ThirdPartyRequest(
             biscuit::ThirdPartyRequest::deserialize_base64(data)
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            Live::new(),
         ))
     }
 
@@ -217,29 +372,47 @@ impl ThirdPartyRequest {
     /// third party service's private key
     #[wasm_bindgen(js_name = createBlock)]
     pub fn create_block(
-        self,
+        &self,
         private_key: &PrivateKey,
         block_builder: &BlockBuilder,
     ) -> Result<ThirdPartyBlock, JsValue> {
         Ok(ThirdPartyBlock(
-            self.0
+            self.copy()?
                 .create_block(&private_key.0, block_builder.0.clone())
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            Live::new(),
         ))
     }
 }
 
+impl ThirdPartyRequest {
+    /// `biscuit::ThirdPartyRequest` is not `Clone`, and `create_block` consumes it,
+    /// so it is copied through its serialized form
+    fn copy(&self) -> Result<biscuit::ThirdPartyRequest, JsValue> {
+        self.0
+            .serialize()
+            .and_then(|data| biscuit::ThirdPartyRequest::deserialize(&data))
+            .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
+    }
+}
+
 #[wasm_bindgen]
-pub struct ThirdPartyBlock(biscuit::ThirdPartyBlock);
+pub struct ThirdPartyBlock(biscuit::ThirdPartyBlock, Live<ThirdPartyBlock>);
 
 #[wasm_bindgen]
 impl ThirdPartyBlock {
+    /// Returns a copy of the block
+    pub fn clone(&self) -> ThirdPartyBlock {
+        ThirdPartyBlock(self.0.clone(), Live::new())
+    }
+
     /// Deserializes a third party request from raw data
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<ThirdPartyRequest, JsValue> {
         Ok(ThirdPartyRequest(
             biscuit::ThirdPartyRequest::deserialize(data)
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            Live::new(),
         ))
     }
 
@@ -251,6 +424,7 @@ impl ThirdPartyBlock {
         Ok(ThirdPartyRequest(
             biscuit::ThirdPartyRequest::deserialize_base64(data)
                 .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())?,
+            Live::new(),
         ))
     }
 
@@ -266,13 +440,14 @@ impl ThirdPartyBlock {
 
     /// Serializes to URL safe base 64 data
     #[wasm_bindgen(js_name = toBase64)]
-    pub fn to_base64(self) -> Result<String, JsValue> {
+    pub fn to_base64(&self) -> Result<String, JsValue> {
         self.0
             .serialize_base64()
             .map_err(|e| serde_wasm_bindgen::to_value(&e).unwrap())
     }
 }
 
+#[cfg(feature = "startup-log")]
 #[wasm_bindgen]
 extern "C" {
     // Use `js_namespace` here to bind `console.log(..)` instead of just
@@ -283,8 +458,9 @@ extern "C" {
 
 #[wasm_bindgen(start)]
 pub fn init() {
-    wasm_logger::init(wasm_logger::Config::default());
+    logger::init();
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
+    #[cfg(feature = "startup-log")]
     log("biscuit-wasm loading")
 }
@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+
+use serde::Serialize;
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+
+thread_local! {
+    /// JS callback receiving the log records, they go to the console if it is not set
+    static SINK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console, js_name = error)]
+    fn console_error(s: &str);
+    #[wasm_bindgen(js_namespace = console, js_name = warn)]
+    fn console_warn(s: &str);
+    #[wasm_bindgen(js_namespace = console, js_name = info)]
+    fn console_info(s: &str);
+    #[wasm_bindgen(js_namespace = console, js_name = debug)]
+    fn console_debug(s: &str);
+}
+
+/// Log record passed to the sink
+#[derive(Serialize)]
+struct LogRecord<'a> {
+    level: &'a str,
+    target: &'a str,
+    message: String,
+}
+
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let level = level_name(record.level());
+        let message = record.args().to_string();
+
+        // the sink is cloned out of the cell, so that it can call `setLogger`
+        match SINK.with(|sink| sink.borrow().clone()) {
+            Some(sink) => {
+                let record = LogRecord {
+                    level,
+                    target: record.target(),
+                    message,
+                };
+                // errors thrown by the sink are ignored, logging must not fail
+                let _ = sink.call1(
+                    &JsValue::NULL,
+                    &serde_wasm_bindgen::to_value(&record).unwrap(),
+                );
+            }
+            None => {
+                let line = format!("{} {}: {}", level, record.target(), message);
+                match record.level() {
+                    log::Level::Error => console_error(&line),
+                    log::Level::Warn => console_warn(&line),
+                    log::Level::Info => console_info(&line),
+                    log::Level::Debug | log::Level::Trace => console_debug(&line),
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn level_name(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "error",
+        log::Level::Warn => "warn",
+        log::Level::Info => "info",
+        log::Level::Debug => "debug",
+        log::Level::Trace => "trace",
+    }
+}
+
+fn parse_level(level: &str) -> Result<log::LevelFilter, JsValue> {
+    level.parse().map_err(|_| {
+        serde_wasm_bindgen::to_value(&format!("unknown log level: {}", level)).unwrap()
+    })
+}
+
+/// Installs the logger, with logging disabled
+pub(crate) fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Off);
+    }
+}
+
+/// Sets the level of the log messages
+///
+/// The level can be one of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+/// Logging is disabled by default
+#[wasm_bindgen(js_name = setLogLevel)]
+pub fn set_log_level(level: &str) -> Result<(), JsValue> {
+    log::set_max_level(parse_level(level)?);
+    Ok(())
+}
+
+/// Configures the logger
+///
+/// The options can contain a `level` (see `setLogLevel`) and a `sink` function,
+/// called with `{ level, target, message }` for each log record. Without a sink,
+/// records are printed to the console. Calling it without options restores the
+/// defaults: logging disabled, records printed to the console
+#[wasm_bindgen(js_name = setLogger)]
+pub fn set_logger(options: JsValue) -> Result<(), JsValue> {
+    if options.is_undefined() || options.is_null() {
+        SINK.with(|s| *s.borrow_mut() = None);
+        log::set_max_level(log::LevelFilter::Off);
+        return Ok(());
+    }
+
+    let level = js_sys::Reflect::get(&options, &JsValue::from_str("level"))?;
+    let level = if level.is_undefined() {
+        None
+    } else {
+        let level = level.as_string().ok_or_else(|| {
+            serde_wasm_bindgen::to_value("the log level must be a string").unwrap()
+        })?;
+        Some(parse_level(&level)?)
+    };
+
+    let sink = js_sys::Reflect::get(&options, &JsValue::from_str("sink"))?;
+    let sink = if sink.is_undefined() || sink.is_null() {
+        None
+    } else {
+        Some(sink.dyn_into::<js_sys::Function>().map_err(|_| {
+            serde_wasm_bindgen::to_value("the log sink must be a function").unwrap()
+        })?)
+    };
+
+    SINK.with(|s| *s.borrow_mut() = sink);
+    if let Some(level) = level {
+        log::set_max_level(level);
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::{cell::RefCell, collections::BTreeMap, marker::PhantomData};
+
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+
+use crate::*;
+
+/// A class exported to JS, whose live objects are counted by `memoryStats`
+pub(crate) trait Class {
+    const NAME: &'static str;
+}
+
+macro_rules! classes {
+    ($($class:ident),* $(,)?) => {
+        $(impl Class for $class {
+            const NAME: &'static str = stringify!($class);
+        })*
+
+        const CLASSES: &[&str] = &[$(stringify!($class)),*];
+    };
+}
+
+classes!(
+    Authorizer,
+    Biscuit,
+    BiscuitBuilder,
+    BlockBuilder,
+    Check,
+    Fact,
+    KeyPair,
+    Policy,
+    PolicySet,
+    PrivateKey,
+    PublicKey,
+    RevocationList,
+    Rule,
+    ThirdPartyBlock,
+    ThirdPartyRequest,
+);
+
+thread_local! {
+    static LIVE: RefCell<BTreeMap<&'static str, usize>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Counts an object of class `T` from its creation until it is dropped
+///
+/// Every exported class holds one, so that objects freed from JS, consumed
+/// by a method or dropped on the Rust side are all accounted for
+pub(crate) struct Live<T: Class>(PhantomData<T>);
+
+impl<T: Class> Live<T> {
+    pub(crate) fn new() -> Self {
+        LIVE.with(|live| *live.borrow_mut().entry(T::NAME).or_default() += 1);
+        Live(PhantomData)
+    }
+}
+
+impl<T: Class> Default for Live<T> {
+    fn default() -> Self {
+        Live::new()
+    }
+}
+
+impl<T: Class> Drop for Live<T> {
+    fn drop(&mut self) {
+        LIVE.with(|live| {
+            if let Some(count) = live.borrow_mut().get_mut(T::NAME) {
+                *count -= 1;
+            }
+        });
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "MemoryStats")]
+    pub type MemoryStats;
+}
+
+/// Returns the number of live objects and the wasm heap size, to help find leaks
+///
+/// Objects are freed when calling `free()`, when leaving the scope of a `using`
+/// declaration, or when they are garbage collected
+#[wasm_bindgen(js_name = memoryStats)]
+pub fn memory_stats() -> MemoryStats {
+    let objects = js_sys::Object::new();
+    LIVE.with(|live| {
+        let live = live.borrow();
+        for class in CLASSES {
+            let count = live.get(class).copied().unwrap_or_default();
+            js_sys::Reflect::set(&objects, &JsValue::from_str(class), &count.into()).unwrap();
+        }
+    });
+
+    let memory = wasm_bindgen::memory()
+        .unchecked_into::<js_sys::WebAssembly::Memory>()
+        .buffer()
+        .unchecked_into::<js_sys::ArrayBuffer>();
+
+    let stats = js_sys::Object::new();
+    js_sys::Reflect::set(&stats, &JsValue::from_str("objects"), &objects).unwrap();
+    js_sys::Reflect::set(
+        &stats,
+        &JsValue::from_str("heapSize"),
+        &memory.byte_length().into(),
+    )
+    .unwrap();
+    stats.unchecked_into()
+}
@@ -0,0 +1,206 @@
+use biscuit_parser::parser::{parse_source, Error};
+use nom::error::ErrorKind;
+use serde::Serialize;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+/// Location of a character in Datalog source code
+///
+/// `line` and `column` start at 0. `column` and `offset` count UTF-16 code units, like
+/// the indices of JS strings
+#[derive(Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+#[derive(Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A parse error, located in the source code
+#[derive(Serialize)]
+pub struct Diagnostic {
+    pub message: String,
+    pub range: Range,
+    pub snippet: String,
+}
+
+/// A fact, rule, check or policy successfully parsed from the source code
+#[derive(Serialize)]
+pub struct Statement {
+    pub source: String,
+    pub range: Range,
+}
+
+#[derive(Serialize, Default)]
+pub struct ParseResult {
+    pub diagnostics: Vec<Diagnostic>,
+    pub facts: Vec<Statement>,
+    pub rules: Vec<Statement>,
+    pub checks: Vec<Statement>,
+    pub policies: Vec<Statement>,
+}
+
+/// Parses Datalog code containing facts, rules, checks and policies
+///
+/// Returns `{ diagnostics, facts, rules, checks, policies }`. Each diagnostic carries
+/// an error message, the range of the offending input and the corresponding snippet.
+/// When there are no diagnostics, the other lists contain the source and range of
+/// every parsed element.
+#[wasm_bindgen(js_name = parseDatalog)]
+pub fn parse_datalog(source: &str) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&parse(source)).map_err(|e| e.into())
+}
+
+pub fn parse(source: &str) -> ParseResult {
+    match parse_source(source) {
+        Ok(result) => ParseResult {
+            diagnostics: Vec::new(),
+            facts: statements(source, result.facts),
+            rules: statements(source, result.rules),
+            checks: statements(source, result.checks),
+            policies: statements(source, result.policies),
+        },
+        Err(errors) => ParseResult {
+            diagnostics: errors.into_iter().map(|e| diagnostic(source, e)).collect(),
+            ..Default::default()
+        },
+    }
+}
+
+fn diagnostic(source: &str, error: Error) -> Diagnostic {
+    let start = offset_in(source, error.input);
+    let rest = &source[start..];
+    let message = error
+        .message
+        .unwrap_or_else(|| error_message(error.code, rest));
+
+    // the snippet goes to the end of the statement, and covers at least one character
+    let snippet = rest
+        .split([';', '\n'])
+        .next()
+        .unwrap_or_default()
+        .trim_end();
+    let (start, snippet) = if !snippet.is_empty() {
+        (start, snippet)
+    } else if let Some(c) = rest.chars().next() {
+        (start, &rest[..c.len_utf8()])
+    } else {
+        // at the end of the input, the last character is reported
+        let before = source.trim_end();
+        match before.chars().next_back() {
+            Some(c) => {
+                let start = before.len() - c.len_utf8();
+                (start, &before[start..])
+            }
+            None => (start, ""),
+        }
+    };
+
+    Diagnostic {
+        message,
+        range: Range {
+            start: position(source, start),
+            end: position(source, start + snippet.len()),
+        },
+        snippet: snippet.to_string(),
+    }
+}
+
+/// describes the errors for which the parser does not provide a message, `rest`
+/// being the source code from the error's position
+fn error_message(code: ErrorKind, rest: &str) -> String {
+    match rest.trim_start().chars().next() {
+        None => "unexpected end of input".to_string(),
+        Some(';') => "unexpected end of statement".to_string(),
+        Some(_) if code == ErrorKind::Tag => "expected a fact, rule, check or policy".to_string(),
+        Some(c) => format!("unexpected character `{}`", c),
+    }
+}
+
+fn statements<T>(source: &str, parsed: Vec<(&str, T)>) -> Vec<Statement> {
+    parsed
+        .into_iter()
+        .map(|(s, _)| {
+            let s = s.trim().trim_end_matches(';');
+            let start = offset_in(source, s);
+            Statement {
+                source: s.to_string(),
+                range: Range {
+                    start: position(source, start),
+                    end: position(source, start + s.len()),
+                },
+            }
+        })
+        .collect()
+}
+
+/// the parser returns slices of the original input, so their
+/// position can be recovered from the pointers
+fn offset_in(source: &str, part: &str) -> usize {
+    (part.as_ptr() as usize)
+        .saturating_sub(source.as_ptr() as usize)
+        .min(source.len())
+}
+
+/// converts a byte offset to a position counted in UTF-16 code units
+fn position(source: &str, offset: usize) -> Position {
+    let before = &source[..offset];
+    Position {
+        line: before.matches('\n').count(),
+        column: before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .encode_utf16()
+            .count(),
+        offset: before.encode_utf16().count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> Diagnostic {
+        let mut diagnostics = parse(source).diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        diagnostics.remove(0)
+    }
+
+    #[test]
+    fn readable_messages() {
+        assert_eq!(error("allow if").message, "unexpected end of input");
+        assert_eq!(error("check if u(;").message, "unexpected end of statement");
+        assert_eq!(
+            error("u(1) v(2)").message,
+            "expected a fact, rule, check or policy"
+        );
+        assert_eq!(
+            error("deny if u(1) trusting foo").message,
+            "unexpected character `f`"
+        );
+    }
+
+    #[test]
+    fn ranges_cover_a_character() {
+        let e = error("check if u(;");
+        assert_eq!(e.snippet, ";");
+        assert_eq!((e.range.start.offset, e.range.end.offset), (11, 12));
+
+        let e = error("allow if ");
+        assert_eq!(e.snippet, "f");
+        assert_eq!((e.range.start.offset, e.range.end.offset), (7, 8));
+    }
+
+    #[test]
+    fn positions_in_utf16() {
+        let e = error("u(\"é\");\nu(\"😀\"); allow if");
+        assert_eq!(e.range.start.line, 1);
+        assert_eq!(e.range.start.column, 16);
+        assert_eq!(e.range.start.offset, 24);
+    }
+}
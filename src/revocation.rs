@@ -0,0 +1,142 @@
+use std::collections::BTreeSet;
+
+use biscuit_auth as biscuit;
+use serde::Serialize;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+use crate::memory::Live;
+
+/// Error returned by the authorizer when the token contains a revoked block
+#[derive(Serialize)]
+pub enum RevocationError {
+    Revoked { block: usize, id: String },
+}
+
+/// Revocation identifier of a block, with the external key of third party blocks
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationInfo {
+    pub block_index: usize,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_key: Option<String>,
+}
+
+/// A set of revoked token identifiers
+///
+/// Identifiers are stored as hexadecimal strings, as returned by
+/// `Biscuit.getRevocationIdentifiers()`
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct RevocationList(pub(crate) BTreeSet<String>, Live<RevocationList>);
+
+#[wasm_bindgen]
+impl RevocationList {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RevocationList {
+        RevocationList(BTreeSet::new(), Live::new())
+    }
+
+    /// Returns a copy of the list
+    pub fn clone(&self) -> RevocationList {
+        RevocationList(self.0.clone(), Live::new())
+    }
+
+    /// Loads a list of hexadecimal identifiers, either as a JSON array of strings, or
+    /// with one identifier per line. Empty lines and lines starting with `#` are ignored
+    #[wasm_bindgen(js_name = fromString)]
+    pub fn from_str(data: &str) -> Result<RevocationList, JsValue> {
+        let data = data.trim();
+        let ids: Vec<String> = if data.starts_with('[') {
+            serde_wasm_bindgen::from_value(js_sys::JSON::parse(data)?)?
+        } else {
+            data.lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string)
+                .collect()
+        };
+
+        let mut list = RevocationList::new();
+        for id in ids {
+            list.add(&id)?;
+        }
+        Ok(list)
+    }
+
+    /// Loads a list of raw identifiers, concatenated
+    ///
+    /// Each identifier is a 64 bytes signature
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(data: &[u8]) -> Result<RevocationList, JsValue> {
+        if !data.len().is_multiple_of(64) {
+            return Err(serde_wasm_bindgen::to_value(&format!(
+                "expected a multiple of 64 bytes, got {}",
+                data.len()
+            ))
+            .unwrap());
+        }
+
+        Ok(RevocationList(
+            data.chunks(64).map(hex::encode).collect(),
+            Live::new(),
+        ))
+    }
+
+    /// Adds a hexadecimal identifier
+    pub fn add(&mut self, id: &str) -> Result<(), JsValue> {
+        hex::decode(id).map_err(|e| {
+            serde_wasm_bindgen::to_value(&format!(
+                "could not deserialize hex encoded revocation id: {}",
+                e
+            ))
+            .unwrap()
+        })?;
+
+        self.0.insert(id.to_lowercase());
+        Ok(())
+    }
+
+    /// Removes a hexadecimal identifier
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.0.remove(&id.to_lowercase())
+    }
+
+    /// Returns true if the list contains the hexadecimal identifier
+    pub fn has(&self, id: &str) -> bool {
+        self.0.contains(&id.to_lowercase())
+    }
+
+    /// Returns the number of identifiers in the list
+    pub fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Serializes to one identifier per line
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_string(&self) -> String {
+        self.0.iter().map(|id| format!("{}\n", id)).collect()
+    }
+}
+
+/// Returns the first revoked block of a token, from its revocation identifiers
+pub(crate) fn check_revoked(
+    revoked: &BTreeSet<String>,
+    ids: &[String],
+) -> Result<(), RevocationError> {
+    match ids.iter().enumerate().find(|(_, id)| revoked.contains(*id)) {
+        Some((block, id)) => Err(RevocationError::Revoked {
+            block,
+            id: id.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn revocation_ids(token: &biscuit::Biscuit) -> Vec<String> {
+    token
+        .revocation_identifiers()
+        .into_iter()
+        .map(hex::encode)
+        .collect()
+}
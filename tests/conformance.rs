@@ -0,0 +1,132 @@
+//! Checks the wasm API against the sample tokens of the Biscuit specification
+//!
+//! The samples are vendored in `tests/samples`, see `tests/samples/README.md`.
+//! Run with `wasm-pack test --node`.
+#![cfg(target_arch = "wasm32")]
+use std::collections::BTreeMap;
+
+use biscuit_wasm::{Biscuit, PublicKey};
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen(module = "fs")]
+extern "C" {
+    #[wasm_bindgen(js_name = readFileSync, catch)]
+    fn read_file_sync(path: &str) -> Result<js_sys::Uint8Array, JsValue>;
+}
+
+#[derive(Deserialize)]
+struct Samples {
+    root_public_key: String,
+    testcases: Vec<TestCase>,
+}
+
+#[derive(Deserialize)]
+struct TestCase {
+    title: String,
+    filename: String,
+    token: Vec<BlockSample>,
+    validations: BTreeMap<String, Validation>,
+}
+
+#[derive(Deserialize)]
+struct BlockSample {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct Validation {
+    result: serde_json::Value,
+    authorizer_code: String,
+    revocation_ids: Vec<String>,
+}
+
+fn read_sample(name: &str) -> Vec<u8> {
+    let path = format!("{}/tests/samples/{}", env!("CARGO_MANIFEST_DIR"), name);
+    match read_file_sync(&path) {
+        Ok(data) => data.to_vec(),
+        Err(_) => panic!(
+            "missing sample file {}, see tests/samples/README.md to vendor the samples",
+            path
+        ),
+    }
+}
+
+/// The default time limit is too short for unoptimized builds
+fn limits() -> JsValue {
+    let limits = js_sys::Object::new();
+    js_sys::Reflect::set(
+        &limits,
+        &"max_time_micro".into(),
+        &JsValue::from_f64(10_000_000.0),
+    )
+    .unwrap();
+    limits.into()
+}
+
+fn to_json(value: JsValue) -> serde_json::Value {
+    serde_wasm_bindgen::from_value(value).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn specification_samples() {
+    let samples: Samples = serde_json::from_slice(&read_sample("samples.json")).unwrap();
+    let root = PublicKey::from_hex(&samples.root_public_key).unwrap();
+
+    for case in samples.testcases {
+        let data = read_sample(&case.filename);
+        let token = match Biscuit::from_bytes(&data, &root) {
+            Ok(token) => token,
+            Err(e) => {
+                // tokens that cannot be parsed are expected to fail in every validation
+                for validation in case.validations.values() {
+                    assert_eq!(
+                        validation.result,
+                        serde_json::json!({ "Err": to_json(e.clone()) }),
+                        "{}: parsing error",
+                        case.title
+                    );
+                }
+                continue;
+            }
+        };
+
+        for (i, block) in case.token.iter().enumerate() {
+            assert_eq!(
+                token.block_source(i).unwrap().trim(),
+                block.code.trim(),
+                "{}: block {} source",
+                case.title,
+                i
+            );
+        }
+
+        for (name, validation) in case.validations.iter() {
+            let revocation_ids: Vec<String> = token
+                .revocation_identifiers()
+                .iter()
+                .map(|id| id.as_string().unwrap())
+                .collect();
+            assert_eq!(
+                revocation_ids, validation.revocation_ids,
+                "{} ({}): revocation ids",
+                case.title, name
+            );
+
+            let result = token.authorizer().and_then(|mut authorizer| {
+                authorizer.add_code(&validation.authorizer_code)?;
+                authorizer.authorizer_with_limits(limits())
+            });
+            let result = match result {
+                Ok(policy) => serde_json::json!({ "Ok": policy }),
+                Err(e) => serde_json::json!({ "Err": to_json(e) }),
+            };
+            assert_eq!(
+                result, validation.result,
+                "{} ({}): authorization result",
+                case.title, name
+            );
+        }
+    }
+}
@@ -0,0 +1,14 @@
+#!/bin/sh
+# Downloads the Biscuit specification samples in this directory
+#
+# usage: ./tests/samples/fetch.sh <biscuit specification commit>
+set -eu
+
+REVISION="${1:?usage: $0 <biscuit specification commit>}"
+BASE="https://raw.githubusercontent.com/biscuit-auth/biscuit/${REVISION}/samples/current"
+DIR="$(dirname "$0")"
+
+curl -sSfL "${BASE}/samples.json" -o "${DIR}/samples.json"
+for file in $(grep -o '"filename": *"[^"]*"' "${DIR}/samples.json" | sed 's/.*"\([^"]*\)"$/\1/'); do
+  curl -sSfL "${BASE}/${file}" -o "${DIR}/${file}"
+done
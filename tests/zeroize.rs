@@ -0,0 +1,146 @@
+//! Checks that private keys do not stay in the wasm memory once freed
+//!
+//! Run with `wasm-pack test --node`.
+#![cfg(target_arch = "wasm32")]
+use biscuit_wasm::{KeyPair, PrivateKey};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::*;
+use zeroize::{Zeroize, Zeroizing};
+
+/// The private key `12aca40167fbdd1a11037e9fd440e3d510d9d9dea70a6646aa4aad784d8a4a8f`
+/// with inverted bits
+///
+/// The test only holds the key inverted, so that the searches do not find its
+/// own copies, in binary or in hexadecimal
+const INVERTED_KEY: [u8; 32] = [
+    0xed, 0x53, 0x5b, 0xfe, 0x98, 0x04, 0x22, 0xe5, //
+    0xee, 0xfc, 0x81, 0x60, 0x2b, 0xbf, 0x1c, 0x2a, //
+    0xef, 0x26, 0x26, 0x21, 0x58, 0xf5, 0x99, 0xb9, //
+    0x55, 0xb5, 0x52, 0x87, 0xb2, 0x75, 0xb5, 0x70, //
+];
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// The hexadecimal form of the key, with inverted bits
+fn inverted_hex() -> Vec<u8> {
+    INVERTED_KEY
+        .iter()
+        .flat_map(|b| [!HEX[(!b >> 4) as usize], !HEX[(!b & 0xf) as usize]])
+        .collect()
+}
+
+/// The key as bytes, `PrivateKey::from_bytes` wipes them
+fn key_bytes() -> Vec<u8> {
+    INVERTED_KEY.iter().map(|b| !b).collect()
+}
+
+/// The key in hexadecimal
+fn key_hex() -> Zeroizing<String> {
+    Zeroizing::new(inverted_hex().iter().map(|c| !c as char).collect())
+}
+
+/// Looks for a pattern, given with inverted bits, in the whole linear memory
+fn in_memory(inverted: &[u8]) -> bool {
+    let memory = wasm_bindgen::memory()
+        .dyn_into::<js_sys::WebAssembly::Memory>()
+        .unwrap();
+    let memory = js_sys::Uint8Array::new(&memory.buffer());
+
+    // the memory is read by chunks overlapping by the pattern length
+    let mut chunk = [0u8; 4096];
+    let mut found = false;
+    let mut start = 0;
+    while !found && start < memory.length() {
+        let end = (start + chunk.len() as u32).min(memory.length());
+        let len = (end - start) as usize;
+        memory.subarray(start, end).copy_to(&mut chunk[..len]);
+
+        found = chunk[..len]
+            .windows(inverted.len())
+            .any(|w| w.iter().zip(inverted).all(|(a, b)| *a == !*b));
+        start = end.saturating_sub(inverted.len() as u32 - 1).max(start + 1);
+        if end == memory.length() {
+            break;
+        }
+    }
+    chunk.zeroize();
+    found
+}
+
+fn key_in_memory() -> bool {
+    in_memory(&INVERTED_KEY)
+}
+
+/// Compares a JS string to the key in hexadecimal, without copying it to the wasm memory
+fn is_key_hex(s: &js_sys::JsString, expected: &[u8]) -> bool {
+    s.length() as usize == expected.len()
+        && expected
+            .iter()
+            .enumerate()
+            .all(|(i, c)| s.char_code_at(i as u32) as u8 == !c)
+}
+
+#[wasm_bindgen_test]
+fn private_key_is_wiped_on_dispose() {
+    let mut key = PrivateKey::from_hex(&key_hex()).unwrap();
+    assert!(
+        key_in_memory(),
+        "the key should be in memory before dispose"
+    );
+
+    key.dispose();
+    assert!(!key_in_memory());
+}
+
+#[wasm_bindgen_test]
+fn keypair_is_wiped_on_dispose() {
+    let mut keypair = KeyPair::from(&PrivateKey::from_hex(&key_hex()).unwrap());
+    keypair.dispose();
+
+    assert!(!key_in_memory());
+}
+
+#[wasm_bindgen_test]
+fn keys_are_wiped_on_drop() {
+    let key = PrivateKey::from_bytes(key_bytes()).unwrap();
+    let keypair = KeyPair::from(&key);
+    let private = keypair.private();
+    assert!(key_in_memory(), "the key should be in memory before drop");
+
+    drop(key);
+    drop(keypair);
+    drop(private);
+    assert!(!key_in_memory());
+}
+
+#[wasm_bindgen_test]
+fn serialization_copies_are_wiped() {
+    // allocated first, so that it does not reuse the memory of a freed copy
+    let hex = inverted_hex();
+    let mut key = PrivateKey::from_bytes(key_bytes()).unwrap();
+    assert!(is_key_hex(&key.to_hex(), &hex));
+
+    let out = js_sys::Uint8Array::new_with_length(32);
+    key.to_bytes(&out).unwrap();
+    // compared byte by byte to avoid copying the key back into the wasm memory
+    assert!((0..32).all(|i| out.get_index(i) == !INVERTED_KEY[i as usize]));
+
+    key.dispose();
+    assert!(!key_in_memory());
+    assert!(!in_memory(&hex));
+}
+
+#[wasm_bindgen_test]
+fn hex_copies_are_wiped_on_drop() {
+    // allocated first, so that it does not reuse the memory of a freed copy
+    let hex = inverted_hex();
+    let key = PrivateKey::from_hex(&key_hex()).unwrap();
+    let keypair = KeyPair::from(&key);
+    assert!(is_key_hex(&key.to_hex(), &hex));
+    assert!(is_key_hex(&keypair.private().to_hex(), &hex));
+
+    drop(key);
+    drop(keypair);
+    assert!(!key_in_memory());
+    assert!(!in_memory(&hex));
+}